@@ -0,0 +1,175 @@
+use std::{
+    env,
+    fs::{read_to_string, write},
+    path::Path,
+};
+
+#[path = "src/preprocessor.rs"]
+mod preprocessor;
+
+const PARAMETERS_SHADERS: [&str; 4] = [
+    "src/fragment.wgsl",
+    "src/blit.wgsl",
+    "src/bloom.wgsl",
+    "src/ssr.wgsl",
+];
+
+fn main() {
+    println!("cargo::rerun-if-changed=src");
+    let source = preprocessor::preprocess(Path::new("src/fragment.wgsl"))
+        .expect("failed to preprocess fragment shader");
+    let out_dir = env::var_os("OUT_DIR").expect("OUT_DIR is not set");
+    write(Path::new(&out_dir).join("fragment.wgsl"), source)
+        .expect("failed to write preprocessed fragment shader");
+    check_parameters_layout();
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Layout {
+    size: u32,
+    align: u32,
+}
+
+struct Field {
+    name: String,
+    offset: u32,
+}
+
+fn check_parameters_layout() {
+    let rust_source = read_to_string("src/parameters.rs").expect("failed to read parameters.rs");
+    let Some((rust_fields, rust_size)) =
+        struct_layout(&rust_source, "pub struct Parameters {", rust_layout)
+    else {
+        return;
+    };
+    for shader in PARAMETERS_SHADERS {
+        let wgsl_source = read_to_string(shader).expect("failed to read shader");
+        let Some((wgsl_fields, wgsl_size)) =
+            struct_layout(&wgsl_source, "struct Parameters {", wgsl_layout)
+        else {
+            continue;
+        };
+        // uniform buffers round structs up to 16 bytes, the rust struct pads that explicitly
+        let wgsl_size = wgsl_size.next_multiple_of(16);
+        let mut rust_fields = rust_fields
+            .iter()
+            .filter(|field| !field.name.starts_with("padding"));
+        for wgsl_field in &wgsl_fields {
+            match rust_fields.next() {
+                Some(rust_field)
+                    if rust_field.name == wgsl_field.name
+                        && rust_field.offset == wgsl_field.offset => {}
+                Some(rust_field) => warn(&format!(
+                    "Parameters mismatch in {shader}: wgsl has {} at offset {}, \
+                    rust has {} at offset {}",
+                    wgsl_field.name, wgsl_field.offset, rust_field.name, rust_field.offset,
+                )),
+                None => warn(&format!(
+                    "Parameters mismatch in {shader}: {} is missing in rust",
+                    wgsl_field.name,
+                )),
+            }
+        }
+        for rust_field in rust_fields {
+            warn(&format!(
+                "Parameters mismatch in {shader}: {} is missing in wgsl",
+                rust_field.name,
+            ));
+        }
+        if rust_size != wgsl_size {
+            warn(&format!(
+                "Parameters size mismatch in {shader}: wgsl is {wgsl_size} bytes, \
+                rust is {rust_size} bytes",
+            ));
+        }
+    }
+}
+
+fn warn(message: &str) {
+    println!("cargo::warning={message}");
+}
+
+// warns and returns None for structs it cannot parse, so that the check is skipped
+fn struct_layout(
+    source: &str,
+    header: &str,
+    field_layout: impl Fn(&str) -> Option<Layout>,
+) -> Option<(Vec<Field>, u32)> {
+    let Some(start) = source.find(header) else {
+        warn(&format!(
+            "Parameters layout not checked: missing {header:?}"
+        ));
+        return None;
+    };
+    let mut fields = Vec::new();
+    let mut offset: u32 = 0;
+    let mut struct_align = 1;
+    for line in source[start + header.len()..].lines() {
+        // comments may contain colons and braces
+        let line = line.split_once("//").map_or(line, |(code, _)| code).trim();
+        if line.starts_with('}') {
+            return Some((fields, offset.next_multiple_of(struct_align)));
+        }
+        if line.starts_with('#') {
+            continue;
+        }
+        let Some((name, ty)) = line.trim_end_matches(',').split_once(':') else {
+            continue;
+        };
+        let (name, ty) = (name.trim(), ty.trim());
+        let Some(layout) = field_layout(ty) else {
+            warn(&format!(
+                "Parameters layout not checked: unknown type {ty} of {name} in {header:?}"
+            ));
+            return None;
+        };
+        offset = offset.next_multiple_of(layout.align);
+        fields.push(Field {
+            name: name.to_string(),
+            offset,
+        });
+        offset += layout.size;
+        struct_align = struct_align.max(layout.align);
+    }
+    warn(&format!(
+        "Parameters layout not checked: unterminated {header:?}"
+    ));
+    None
+}
+
+fn rust_layout(ty: &str) -> Option<Layout> {
+    match ty {
+        "u8" => Some(Layout { size: 1, align: 1 }),
+        "f32" | "u32" | "i32" => Some(Layout { size: 4, align: 4 }),
+        _ => {
+            let (element, count) = ty.strip_prefix('[')?.strip_suffix(']')?.rsplit_once(';')?;
+            let element = rust_layout(element.trim())?;
+            let count: u32 = count.trim().parse().ok()?;
+            Some(Layout {
+                size: element.size * count,
+                align: element.align,
+            })
+        }
+    }
+}
+
+fn wgsl_layout(ty: &str) -> Option<Layout> {
+    let ty = ty.replace("Scalar", "f32");
+    match ty.as_str() {
+        "f32" | "u32" | "i32" => Some(Layout { size: 4, align: 4 }),
+        "vec2<f32>" | "vec2<u32>" | "vec2<i32>" => Some(Layout { size: 8, align: 8 }),
+        "vec3<f32>" | "vec3<u32>" | "vec3<i32>" => Some(Layout {
+            size: 12,
+            align: 16,
+        }),
+        "vec4<f32>" | "vec4<u32>" | "vec4<i32>" => Some(Layout {
+            size: 16,
+            align: 16,
+        }),
+        "mat4x4<f32>" => Some(Layout {
+            size: 64,
+            align: 16,
+        }),
+        _ => None,
+    }
+}
@@ -1,19 +1,94 @@
-use crate::initialized_app::InitializedApp;
+use crate::{
+    arguments::Arguments, initialized_app::InitializedApp, persistent_graphics::PersistentGraphics,
+    render_error::RenderError,
+};
 use anyhow::{Context, Result};
 use pollster::block_on;
+use std::{mem, sync::Arc};
+use wgpu::SurfaceError;
 use winit::{
-    application::ApplicationHandler, event::WindowEvent, event_loop::ActiveEventLoop,
-    window::WindowId,
+    application::ApplicationHandler,
+    event::{ElementState, WindowEvent},
+    event_loop::ActiveEventLoop,
+    window::{Window, WindowId},
 };
 
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct App {
-    initialized: Option<InitializedApp>,
+    arguments: Arguments,
+    lifecycle: Lifecycle,
+    // keeps the window open after an error, so that its title can show the error
+    error_window: Option<Arc<Window>>,
+}
+
+#[derive(Debug)]
+#[allow(clippy::large_enum_variant)] // there is only one app, which is running most of the time
+enum Lifecycle {
+    Uninitialized,
+    Initializing,
+    Running(InitializedApp),
+    // shown until a key is pressed
+    Error(String),
 }
 
 impl App {
-    fn initialized_mut(&mut self, context: &'static str) -> &mut InitializedApp {
-        self.initialized.as_mut().context(context).unwrap()
+    pub fn new(arguments: Arguments) -> Self {
+        Self {
+            arguments,
+            lifecycle: Lifecycle::Uninitialized,
+            error_window: None,
+        }
+    }
+
+    fn take_running(&mut self) -> Option<InitializedApp> {
+        match mem::replace(&mut self.lifecycle, Lifecycle::Uninitialized) {
+            Lifecycle::Running(initialized) => Some(initialized),
+            lifecycle => {
+                self.lifecycle = lifecycle;
+                None
+            }
+        }
+    }
+
+    fn draw(&mut self) -> Result<()> {
+        let Lifecycle::Running(initialized) = &mut self.lifecycle else {
+            return Ok(());
+        };
+        let Err(error) = initialized.draw() else {
+            return Ok(());
+        };
+        match error {
+            RenderError::Surface(SurfaceError::Timeout) => {
+                eprintln!("timed out getting frame texture, retrying");
+                initialized.request_redraw();
+            }
+            RenderError::Surface(SurfaceError::Lost) => {
+                eprintln!("surface lost, reinitializing graphics");
+                let initialized = self
+                    .take_running()
+                    .context("got redraw before initialization")?;
+                let initialized = block_on(initialized.reinitialize_graphics(&self.arguments))?;
+                eprintln!("reinitialized graphics");
+                self.lifecycle = Lifecycle::Running(initialized);
+                if let Lifecycle::Running(initialized) = &mut self.lifecycle {
+                    initialized
+                        .draw()
+                        .context("failed to draw after reinitializing graphics")?;
+                }
+            }
+            RenderError::Surface(SurfaceError::OutOfMemory) => {
+                eprintln!("out of memory, halving the render texture size");
+                if !initialized.halve_render_texture_size() {
+                    return Err(error).context("render texture size is already minimal");
+                }
+                initialized
+                    .draw()
+                    .context("failed to draw with halved render texture size")?;
+                eprintln!("drew with halved render texture size");
+            }
+            _ => return Err(error.into()),
+        }
+        Ok(())
     }
 
     fn handle_window_event(
@@ -21,54 +96,91 @@ impl App {
         event_loop: &ActiveEventLoop,
         event: WindowEvent,
     ) -> Result<()> {
+        if let WindowEvent::RedrawRequested = event {
+            return self.draw().context("failed to draw");
+        }
+        let Lifecycle::Running(initialized) = &mut self.lifecycle else {
+            return Ok(());
+        };
         match event {
             WindowEvent::CloseRequested => {
                 event_loop.exit();
             }
-            WindowEvent::RedrawRequested => {
-                self.initialized_mut("got redraw before initialization")
-                    .draw()
-                    .context("failed to draw")?;
-            }
             WindowEvent::Resized(..) => {
-                self.initialized_mut("got resize before initialization")
-                    .resize()
-                    .context("failed to resize")?;
+                initialized.resize().context("failed to resize")?;
             }
             WindowEvent::KeyboardInput { event, .. } => {
-                self.initialized_mut("got keyboard input before initialization")
-                    .handle_key(&event);
+                initialized.handle_key(&event);
+                if initialized.is_exit_requested() {
+                    event_loop.exit();
+                }
             }
             WindowEvent::CursorMoved { position, .. } => {
-                self.initialized_mut("got cursor movement before initialization")
+                initialized
                     .handle_cursor_movement(position)
                     .context("failed to handle cursor movement")?;
             }
             WindowEvent::MouseInput { state, button, .. } => {
-                self.initialized_mut("got mouse input before initialization")
-                    .handle_mouse(button, state);
+                initialized.handle_mouse(button, state);
             }
             WindowEvent::MouseWheel { delta, .. } => {
-                self.initialized_mut("got mouse wheel before initializtation")
-                    .handle_mouse_wheel(delta);
+                initialized.handle_mouse_wheel(delta);
             }
             WindowEvent::Focused(focused) => {
-                self.initialized_mut("got focused before initialization")
-                    .handle_focused(focused);
+                initialized.handle_focused(focused);
+            }
+            WindowEvent::Occluded(occluded) => {
+                initialized.handle_occluded(occluded);
+            }
+            WindowEvent::ThemeChanged(theme) => {
+                initialized.handle_theme_changed(theme);
+            }
+            WindowEvent::DroppedFile(path) => {
+                // a bad drop should not stop the app
+                if let Err(error) = initialized.handle_drop(&path) {
+                    eprintln!("{error:?}");
+                }
             }
             _ => {}
         }
         Ok(())
     }
+
+    fn fail(&mut self, event_loop: &ActiveEventLoop, error: anyhow::Error) {
+        eprintln!("{error:?}");
+        let window = self
+            .take_running()
+            .and_then(|initialized| initialized.shared_window());
+        let Some(window) = window else {
+            // without a window, there is nowhere to show the error
+            event_loop.exit();
+            return;
+        };
+        window.set_cursor_visible(true);
+        self.error_window = Some(window);
+        self.lifecycle = Lifecycle::Error(format!("{error:#}"));
+        self.show_error();
+    }
+
+    fn show_error(&self) {
+        let (Lifecycle::Error(error), Some(window)) = (&self.lifecycle, &self.error_window) else {
+            return;
+        };
+        let title = PersistentGraphics::TITLE;
+        window.set_title(&format!("{title} [ERROR: {error}] (press any key to exit)"));
+    }
 }
 
 impl ApplicationHandler for App {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
-        self.initialized = Some(
-            block_on(InitializedApp::init(event_loop))
-                .context("failed to initialize app")
-                .unwrap(),
-        );
+        if !matches!(self.lifecycle, Lifecycle::Uninitialized) {
+            return;
+        }
+        self.lifecycle = Lifecycle::Initializing;
+        match block_on(InitializedApp::init(event_loop, &self.arguments)) {
+            Ok(initialized) => self.lifecycle = Lifecycle::Running(initialized),
+            Err(error) => self.fail(event_loop, error.context("failed to initialize app")),
+        }
     }
 
     fn window_event(
@@ -77,6 +189,22 @@ impl ApplicationHandler for App {
         _window_id: WindowId,
         event: WindowEvent,
     ) {
-        self.handle_window_event(event_loop, event).unwrap();
+        match &self.lifecycle {
+            Lifecycle::Uninitialized | Lifecycle::Initializing => {}
+            Lifecycle::Running(_) => {
+                if let Err(error) = self.handle_window_event(event_loop, event) {
+                    self.fail(event_loop, error);
+                }
+            }
+            Lifecycle::Error(_) => match event {
+                WindowEvent::CloseRequested => event_loop.exit(),
+                WindowEvent::KeyboardInput { event, .. }
+                    if event.state == ElementState::Pressed =>
+                {
+                    event_loop.exit()
+                }
+                _ => {}
+            },
+        }
     }
 }
@@ -0,0 +1,137 @@
+use anyhow::{Context, Result, bail};
+use std::{env::args, path::PathBuf};
+
+#[derive(Debug, Default)]
+pub struct Arguments {
+    pub headless: bool,
+    pub log_fps: Option<PathBuf>,
+    pub gpu: Option<String>,
+    pub orbit_speed: Option<f32>,
+    pub strict: bool,
+    pub midi: bool,
+    pub benchmark: Option<u32>,
+    pub mouse_sensitivity: Option<f32>,
+    pub smooth_alpha: Option<f32>,
+    pub aspect: Option<(u32, u32)>,
+    pub relaxed_limits: bool,
+    pub kiosk: bool,
+    pub no_vsync: bool,
+    pub split_submit: bool,
+    pub gpu_info: bool,
+    pub frame_budget_ms: Option<f32>,
+    pub time_factor_limits: Option<(f32, f32)>,
+}
+
+impl Arguments {
+    pub fn parse() -> Result<Self> {
+        let mut arguments = Self::default();
+        let mut args = args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--headless" => arguments.headless = true,
+                "--strict" => arguments.strict = true,
+                "--midi" => arguments.midi = true,
+                "--relaxed-limits" => arguments.relaxed_limits = true,
+                "--kiosk" => arguments.kiosk = true,
+                "--no-vsync" => arguments.no_vsync = true,
+                "--split-submit" => arguments.split_submit = true,
+                "--gpu-info" => arguments.gpu_info = true,
+                "--log-fps" => {
+                    let path = args.next().context("--log-fps requires a path")?;
+                    arguments.log_fps = Some(path.into());
+                }
+                "--gpu" => {
+                    let name = args.next().context("--gpu requires an adapter name")?;
+                    arguments.gpu = Some(name);
+                }
+                "--orbit-speed" => {
+                    let speed = args.next().context("--orbit-speed requires a speed")?;
+                    let speed = speed
+                        .parse()
+                        .with_context(|| format!("invalid orbit speed: {speed}"))?;
+                    arguments.orbit_speed = Some(speed);
+                }
+                "--benchmark" => {
+                    let frames = args.next().context("--benchmark requires a frame count")?;
+                    let frames = frames
+                        .parse()
+                        .with_context(|| format!("invalid frame count: {frames}"))?;
+                    arguments.benchmark = Some(frames);
+                }
+                "--mouse-sensitivity" => {
+                    let pixels = args
+                        .next()
+                        .context("--mouse-sensitivity requires a pixel count")?;
+                    let pixels: f32 = pixels
+                        .parse()
+                        .with_context(|| format!("invalid mouse sensitivity: {pixels}"))?;
+                    if pixels <= 0.0 {
+                        bail!("mouse sensitivity must be positive: {pixels}");
+                    }
+                    arguments.mouse_sensitivity = Some(pixels);
+                }
+                "--smooth-alpha" => {
+                    let alpha = args.next().context("--smooth-alpha requires an alpha")?;
+                    let alpha: f32 = alpha
+                        .parse()
+                        .with_context(|| format!("invalid smooth alpha: {alpha}"))?;
+                    // zero would freeze the smoothed camera in place
+                    if !(alpha > 0.0 && alpha <= 1.0) {
+                        bail!("smooth alpha must be in (0, 1]: {alpha}");
+                    }
+                    arguments.smooth_alpha = Some(alpha);
+                }
+                "--frame-budget" => {
+                    let ms = args
+                        .next()
+                        .context("--frame-budget requires a duration in milliseconds")?;
+                    let ms: f32 = ms
+                        .parse()
+                        .with_context(|| format!("invalid frame budget: {ms}"))?;
+                    if ms <= 0.0 {
+                        bail!("frame budget must be positive: {ms}");
+                    }
+                    arguments.frame_budget_ms = Some(ms);
+                }
+                "--time-factor-limits" => {
+                    let limits = args
+                        .next()
+                        .context("--time-factor-limits requires limits like -8:8")?;
+                    let (min, max) = limits.split_once(':').with_context(|| {
+                        format!("time factor limits are missing a colon: {limits}")
+                    })?;
+                    let parse = |limit: &str| -> Result<f32> {
+                        limit
+                            .parse()
+                            .with_context(|| format!("invalid time factor limits: {limits}"))
+                    };
+                    let (min, max) = (parse(min)?, parse(max)?);
+                    if !min.is_finite() || !max.is_finite() {
+                        bail!("time factor limits must be finite: {limits}");
+                    }
+                    if min > max {
+                        bail!("the minimum time factor is above the maximum: {limits}");
+                    }
+                    arguments.time_factor_limits = Some((min, max));
+                }
+                "--aspect" => {
+                    let aspect = args.next().context("--aspect requires a ratio like 16:9")?;
+                    let (width, height) = aspect
+                        .split_once(':')
+                        .with_context(|| format!("aspect ratio is missing a colon: {aspect}"))?;
+                    let parse = |side: &str| {
+                        side.parse()
+                            .with_context(|| format!("invalid aspect ratio: {aspect}"))
+                    };
+                    let (width, height) = (parse(width)?, parse(height)?);
+                    if width == 0 || height == 0 {
+                        bail!("aspect ratio sides must be positive: {aspect}");
+                    }
+                    arguments.aspect = Some((width, height));
+                }
+                _ => bail!("unknown argument: {arg}"),
+            }
+        }
+        Ok(arguments)
+    }
+}
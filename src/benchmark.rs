@@ -0,0 +1,33 @@
+use crate::{arguments::Arguments, initialized_app::InitializedApp};
+use anyhow::{Context, Result};
+use pollster::block_on;
+
+#[derive(Debug, Clone, Copy)]
+pub struct BenchmarkResult {
+    pub total_ms: f64,
+    pub mean_fps: f32,
+    pub min_fps: f32,
+    pub p99_fps: f32,
+}
+
+impl BenchmarkResult {
+    pub fn to_json(self) -> String {
+        let Self {
+            total_ms,
+            mean_fps,
+            min_fps,
+            p99_fps,
+        } = self;
+        format!(
+            r#"{{"total_ms": {total_ms:.3}, "mean_fps": {mean_fps:.3}, "min_fps": {min_fps:.3}, "p99_fps": {p99_fps:.3}}}"#
+        )
+    }
+}
+
+pub fn run(arguments: &Arguments, frames: u32) -> Result<()> {
+    let mut app = block_on(InitializedApp::init_off_screen(arguments))
+        .context("failed to initialize benchmark app")?;
+    let result = app.benchmark(frames)?;
+    println!("{}", result.to_json());
+    Ok(())
+}
@@ -1,11 +1,144 @@
+struct Parameters {
+    camera_matrix: mat4x4<f32>,
+    aspect_scale: vec2<f32>,
+    time: f32,
+    num_iterations: u32,
+    scene_index: u32,
+    fractal_power: f32,
+    dither_enabled: u32,
+    bloom_enabled: u32,
+    bloom_threshold: f32,
+    bloom_strength: f32,
+    viewport_count: u32,
+    step_factor: f32,
+    chromatic_aberration: f32,
+    mandelbox_scale: f32,
+    mandelbox_fold_radius: f32,
+    background_color_top: vec3<f32>,
+    background_color_bottom: vec3<f32>,
+    interior_mode: u32,
+    orbit_target: vec3<f32>,
+    show_orbit_target: u32,
+    normal_epsilon: f32,
+    camera_matrix_b: mat4x4<f32>,
+    blend_views: f32,
+    jitter: vec2<f32>,
+    jitter_enabled: u32,
+    wall_time: f32,
+    sharpen_strength: f32,
+    film_grain: f32,
+    seed: u32,
+    debug_mode: u32,
+    letterbox_scale: vec2<f32>,
+    scanline_strength: f32,
+    ssr_strength: f32,
+    time_b: f32,
+}
+
 @group(0) @binding(0)
 var texture: texture_2d<f32>;
 @group(0) @binding(1)
 var texture_sampler: sampler;
+@group(1) @binding(0)
+var<uniform> parameters: Parameters;
+@group(2) @binding(0)
+var bloom_texture: texture_2d<f32>;
+@group(2) @binding(1)
+var bloom_texture_sampler: sampler;
+@group(3) @binding(0)
+var ssr_texture: texture_2d<f32>;
+@group(3) @binding(1)
+var ssr_texture_sampler: sampler;
+
+const BAYER_MATRIX = array<f32, 16>(
+     0,  8,  2, 10,
+    12,  4, 14,  6,
+     3, 11,  1,  9,
+    15,  7, 13,  5,
+);
+
+fn dither(color: vec3<f32>, pixel: vec2<u32>) -> vec3<f32> {
+    var bayer_matrix = BAYER_MATRIX;
+    let position = pixel % 4;
+    let threshold = (bayer_matrix[position.y * 4 + position.x] + 0.5) / 16;
+    return color + (threshold - 0.5) / 255;
+}
+
+// interleaved gradient noise, offset every frame so the grain moves
+fn film_grain_noise(pixel: vec2<f32>) -> f32 {
+    const FRAMES_PER_SECOND = 60.0;
+    let frame = floor(parameters.wall_time * FRAMES_PER_SECOND) % 1024;
+    let position = pixel + 5.588238 * frame;
+    return fract(52.9829189 * fract(dot(position, vec2(0.06711056, 0.00583715))));
+}
+
+// darkens every odd output row, leaving the even ones untouched
+fn scanlines(color: vec3<f32>, frag_coord_y: f32) -> vec3<f32> {
+    const PI = 3.141592653589793238;
+    const SCANLINE_PITCH = 2.0;
+    let darkening = pow(sin(floor(frag_coord_y) * PI / SCANLINE_PITCH), 2);
+    return color * (1 - parameters.scanline_strength * darkening);
+}
+
+fn sample_with_chromatic_aberration(uv: vec2<f32>) -> vec4<f32> {
+    // the offset is relative to the height, so it looks the same for any aspect ratio
+    let offset = vec2(parameters.chromatic_aberration * parameters.aspect_scale.y / parameters.aspect_scale.x, 0);
+    let center = textureSample(texture, texture_sampler, uv);
+    let red = textureSample(texture, texture_sampler, uv - offset).r;
+    let blue = textureSample(texture, texture_sampler, uv + offset).b;
+    return vec4(red, center.g, blue, center.a);
+}
+
+// unsharp mask against a 3x3 box blur of the render texture
+fn sharpen(color: vec4<f32>, uv: vec2<f32>, upscale_amount: f32) -> vec4<f32> {
+    let texel_size = 1 / vec2<f32>(textureDimensions(texture));
+    var blurred = vec4<f32>(0);
+    for (var y = -1; y <= 1; y++) {
+        for (var x = -1; x <= 1; x++) {
+            blurred += textureSampleLevel(texture, texture_sampler, uv + vec2(f32(x), f32(y)) * texel_size, 0);
+        }
+    }
+    blurred /= 9;
+    // the blurriness only comes from upscaling, so full resolution stays untouched
+    let strength = parameters.sharpen_strength * upscale_amount;
+    return color + strength * (color - blurred);
+}
 
 @fragment
-fn fragment_main(@location(0) screen_position: vec2<f32>) -> @location(0) vec4<f32> {
-    let flipped_uv = (screen_position + 1) * 0.5;
+fn fragment_main(
+    @builtin(position) frag_coord: vec4<f32>,
+    @location(0) screen_position: vec2<f32>,
+) -> @location(0) vec4<f32> {
+    let image_position = screen_position / parameters.letterbox_scale;
+    let flipped_uv = (image_position + 1) * 0.5;
     let uv = vec2(flipped_uv.x, 1 - flipped_uv.y);
-    return textureSample(texture, texture_sampler, uv);
+    // render texture texels per output pixel, below 1 when upscaling
+    let texels_per_pixel = abs(dpdx(uv.x)) * f32(textureDimensions(texture).x);
+    var color = sample_with_chromatic_aberration(uv);
+    if (parameters.sharpen_strength != 0) {
+        color = sharpen(color, uv, saturate(1 - texels_per_pixel));
+    }
+    let bloom = textureSample(bloom_texture, bloom_texture_sampler, uv);
+    if (parameters.bloom_enabled != 0) {
+        color += vec4(parameters.bloom_strength * bloom.rgb, 0);
+    }
+    let reflection = textureSample(ssr_texture, ssr_texture_sampler, uv);
+    if (parameters.ssr_strength != 0) {
+        color += vec4(parameters.ssr_strength * reflection.rgb, 0);
+    }
+    if (parameters.film_grain != 0) {
+        let grain = parameters.film_grain * (film_grain_noise(frag_coord.xy) - 0.5) / 255;
+        color += vec4(vec3(grain), 0);
+    }
+    if (parameters.scanline_strength != 0) {
+        color = vec4(scanlines(color.rgb, frag_coord.y), color.a);
+    }
+    if (parameters.dither_enabled != 0) {
+        color = vec4(dither(color.rgb, vec2<u32>(frag_coord.xy)), color.a);
+    }
+    // masked at the end, because sampling with derivatives needs uniform control flow
+    if (any(abs(image_position) > vec2(1))) {
+        return vec4(0, 0, 0, 1);
+    }
+    return color;
 }
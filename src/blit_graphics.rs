@@ -7,15 +7,27 @@ use wgpu::{
 #[derive(Debug)]
 pub struct BlitGraphics {
     pub render_texture: Texture,
+    format: TextureFormat,
     pub blit_bind_group: BindGroup,
 }
 
 impl BlitGraphics {
-    pub const RENDER_TEXTURE_FORMAT: TextureFormat = TextureFormat::Rgba8UnormSrgb;
+    pub fn default_format() -> TextureFormat {
+        TextureFormat::Rgba8UnormSrgb
+    }
+
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+
+    pub fn render_texture_size(&self) -> (u32, u32) {
+        (self.render_texture.width(), self.render_texture.height())
+    }
 
     pub fn init(
         persistent: &PersistentGraphics,
         render_texture_config: &RenderTextureConfig,
+        format: TextureFormat,
     ) -> Self {
         let PersistentGraphics {
             device,
@@ -24,7 +36,10 @@ impl BlitGraphics {
             ..
         } = persistent;
         let render_texture = {
-            let (width, height) = render_texture_config.render_texture_size();
+            let (surface_width, surface_height) = persistent.surface_size();
+            let (width, height) =
+                render_texture_config.render_texture_size(surface_width, surface_height);
+            assert!(width > 0 && height > 0, "render texture would be empty");
             device.create_texture(&TextureDescriptor {
                 label: Some("render_texture"),
                 dimension: TextureDimension::D2,
@@ -35,7 +50,7 @@ impl BlitGraphics {
                 },
                 mip_level_count: 1,
                 sample_count: 1,
-                format: Self::RENDER_TEXTURE_FORMAT,
+                format,
                 usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
                 view_formats: &[],
             })
@@ -57,6 +72,7 @@ impl BlitGraphics {
         });
         Self {
             render_texture,
+            format,
             blit_bind_group,
         }
     }
@@ -0,0 +1,85 @@
+struct Parameters {
+    camera_matrix: mat4x4<f32>,
+    aspect_scale: vec2<f32>,
+    time: f32,
+    num_iterations: u32,
+    scene_index: u32,
+    fractal_power: f32,
+    dither_enabled: u32,
+    bloom_enabled: u32,
+    bloom_threshold: f32,
+    bloom_strength: f32,
+    viewport_count: u32,
+    step_factor: f32,
+    chromatic_aberration: f32,
+    mandelbox_scale: f32,
+    mandelbox_fold_radius: f32,
+    background_color_top: vec3<f32>,
+    background_color_bottom: vec3<f32>,
+    interior_mode: u32,
+    orbit_target: vec3<f32>,
+    show_orbit_target: u32,
+    normal_epsilon: f32,
+    camera_matrix_b: mat4x4<f32>,
+    blend_views: f32,
+    jitter: vec2<f32>,
+    jitter_enabled: u32,
+    wall_time: f32,
+    sharpen_strength: f32,
+    film_grain: f32,
+    seed: u32,
+    debug_mode: u32,
+    letterbox_scale: vec2<f32>,
+    scanline_strength: f32,
+    ssr_strength: f32,
+    time_b: f32,
+}
+
+@group(0) @binding(0)
+var texture: texture_2d<f32>;
+@group(0) @binding(1)
+var texture_sampler: sampler;
+@group(1) @binding(0)
+var<uniform> parameters: Parameters;
+
+// one side of a gaussian kernel with a radius of 5 pixels (sigma = 2)
+const BLUR_RADIUS = 5;
+const BLUR_WEIGHTS = array<f32, 6>(0.2006, 0.1770, 0.1216, 0.0651, 0.0271, 0.0088);
+
+fn screen_to_uv(screen_position: vec2<f32>) -> vec2<f32> {
+    let flipped_uv = (screen_position + 1) * 0.5;
+    return vec2(flipped_uv.x, 1 - flipped_uv.y);
+}
+
+@fragment
+fn threshold_main(@location(0) screen_position: vec2<f32>) -> @location(0) vec4<f32> {
+    let color = textureSample(texture, texture_sampler, screen_to_uv(screen_position)).rgb;
+    let brightness = max(max(color.r, color.g), color.b);
+    let bright_part = max(brightness - parameters.bloom_threshold, 0) / max(brightness, 1e-4);
+    return vec4(color * bright_part, 1);
+}
+
+fn blur(screen_position: vec2<f32>, direction: vec2<f32>) -> vec4<f32> {
+    var blur_weights = BLUR_WEIGHTS;
+    let uv = screen_to_uv(screen_position);
+    let step = direction / vec2<f32>(textureDimensions(texture));
+    var color = blur_weights[0] * textureSample(texture, texture_sampler, uv).rgb;
+    for (var i = 1; i <= BLUR_RADIUS; i++) {
+        let offset = f32(i) * step;
+        color += blur_weights[i] * (
+            textureSample(texture, texture_sampler, uv + offset).rgb +
+            textureSample(texture, texture_sampler, uv - offset).rgb
+        );
+    }
+    return vec4(color, 1);
+}
+
+@fragment
+fn blur_horizontal_main(@location(0) screen_position: vec2<f32>) -> @location(0) vec4<f32> {
+    return blur(screen_position, vec2(1, 0));
+}
+
+@fragment
+fn blur_vertical_main(@location(0) screen_position: vec2<f32>) -> @location(0) vec4<f32> {
+    return blur(screen_position, vec2(0, 1));
+}
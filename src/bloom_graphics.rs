@@ -0,0 +1,142 @@
+use crate::{
+    blit_graphics::BlitGraphics,
+    persistent_graphics::PersistentGraphics,
+    utils::{RenderPipelineSpec, create_render_pipeline},
+};
+use std::borrow::Cow;
+use wgpu::{
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindingResource, Extent3d, RenderPipeline,
+    ShaderModuleDescriptor, ShaderSource, Texture, TextureDescriptor, TextureDimension,
+    TextureFormat, TextureUsages, TextureView, TextureViewDescriptor,
+};
+
+#[derive(Debug)]
+pub struct BloomPipelines {
+    pub threshold_render_pipeline: RenderPipeline,
+    pub blur_horizontal_render_pipeline: RenderPipeline,
+    pub blur_vertical_render_pipeline: RenderPipeline,
+}
+
+impl BloomPipelines {
+    pub fn init(persistent: &PersistentGraphics, format: TextureFormat) -> Self {
+        let PersistentGraphics {
+            device,
+            vertex_shader,
+            blit_bind_group_layout,
+            parameters_bind_group_layout,
+            ..
+        } = persistent;
+        let bloom_fragment_shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("bloom_fragment_shader"),
+            source: ShaderSource::Wgsl(Cow::Borrowed(include_str!("./bloom.wgsl"))),
+        });
+        let create_bloom_render_pipeline = |label, fragment_entry_point| {
+            create_render_pipeline(
+                device,
+                &RenderPipelineSpec {
+                    layout_label: "bloom_render_pipeline_layout",
+                    bind_group_layouts: &[blit_bind_group_layout, parameters_bind_group_layout],
+                    push_constant_ranges: &[],
+                    label,
+                    vertex_shader,
+                    fragment_shader: &bloom_fragment_shader,
+                    fragment_entry_point,
+                    texture_formats: &[format],
+                },
+            )
+        };
+        Self {
+            threshold_render_pipeline: create_bloom_render_pipeline(
+                "bloom_threshold_render_pipeline",
+                "threshold_main",
+            ),
+            blur_horizontal_render_pipeline: create_bloom_render_pipeline(
+                "bloom_blur_horizontal_render_pipeline",
+                "blur_horizontal_main",
+            ),
+            blur_vertical_render_pipeline: create_bloom_render_pipeline(
+                "bloom_blur_vertical_render_pipeline",
+                "blur_vertical_main",
+            ),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct BloomGraphics {
+    pub bright_texture_view: TextureView,
+    pub blurred_texture_view: TextureView,
+    pub threshold_bind_group: BindGroup,
+    pub blur_horizontal_bind_group: BindGroup,
+    pub blur_vertical_bind_group: BindGroup,
+    pub composite_bind_group: BindGroup,
+}
+
+impl BloomGraphics {
+    pub fn init(persistent: &PersistentGraphics, blit: &BlitGraphics) -> Self {
+        let PersistentGraphics {
+            device,
+            render_texture_sampler,
+            blit_bind_group_layout,
+            ..
+        } = persistent;
+        let (width, height) = blit.render_texture_size();
+        let create_bloom_texture = |label| {
+            device.create_texture(&TextureDescriptor {
+                label: Some(label),
+                dimension: TextureDimension::D2,
+                size: Extent3d {
+                    width: (width / 2).max(1),
+                    height: (height / 2).max(1),
+                    ..Default::default()
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                format: blit.format(),
+                usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            })
+        };
+        let view = |texture: &Texture| texture.create_view(&TextureViewDescriptor::default());
+        let create_texture_bind_group = |label, texture_view: &TextureView| {
+            device.create_bind_group(&BindGroupDescriptor {
+                label: Some(label),
+                layout: blit_bind_group_layout,
+                entries: &[
+                    BindGroupEntry {
+                        binding: 0,
+                        resource: BindingResource::TextureView(texture_view),
+                    },
+                    BindGroupEntry {
+                        binding: 1,
+                        resource: BindingResource::Sampler(render_texture_sampler),
+                    },
+                ],
+            })
+        };
+        // the vertical blur writes back into the bright texture, so that is what gets composited
+        let bright_texture_view = view(&create_bloom_texture("bloom_bright_texture"));
+        let blurred_texture_view = view(&create_bloom_texture("bloom_blurred_texture"));
+        let render_texture_view = view(&blit.render_texture);
+        Self {
+            threshold_bind_group: create_texture_bind_group(
+                "bloom_threshold_bind_group",
+                &render_texture_view,
+            ),
+            blur_horizontal_bind_group: create_texture_bind_group(
+                "bloom_blur_horizontal_bind_group",
+                &bright_texture_view,
+            ),
+            blur_vertical_bind_group: create_texture_bind_group(
+                "bloom_blur_vertical_bind_group",
+                &blurred_texture_view,
+            ),
+            composite_bind_group: create_texture_bind_group(
+                "bloom_composite_bind_group",
+                &bright_texture_view,
+            ),
+            bright_texture_view,
+            blurred_texture_view,
+        }
+    }
+}
@@ -1,19 +1,57 @@
-use crate::{held_keys::HeldKeys, utils::limited_quadratric_delta};
-use cgmath::{Angle, InnerSpace, Matrix3, Matrix4, Rad, Vector2, Vector3, Zero, num_traits::clamp};
-use std::{f32::consts::FRAC_PI_2, time::Duration};
+use crate::{
+    held_keys::HeldKeys,
+    presets::FractalPreset,
+    utils::{limited_quadratic_delta, parse_key_value_lines},
+};
+use anyhow::{Context, Result, anyhow};
+use cgmath::{
+    Angle, InnerSpace, Matrix3, Matrix4, Rad, Vector2, Vector3, VectorSpace, Zero,
+    num_traits::clamp,
+};
+use std::{
+    collections::HashMap,
+    f32::consts::{FRAC_PI_2, FRAC_PI_4, PI},
+    time::Duration,
+};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Camera {
     movement_per_second: f32,
+    // the fraction of movement_per_second, ramped up and down instead of jumping
+    movement_velocity: f32,
+    // keeps the camera gliding along the last movement after the keys are released
+    movement_direction: Vector3<f32>,
+    fine_movement: bool,
+    vertical_speed_multiplier: f32,
+    rotation_per_pixel: Rad<f32>,
+    speed_mode: SpeedMode,
     orbit_angle_per_second: Rad<f32>,
     lock_yaw_mode: LockYawMode,
     lock_pitch: bool,
+    // lets the pitch go over the poles instead of stopping there
+    unconstrained_pitch: bool,
+    auto_correct_roll: bool,
+    orbit_target: Vector3<f32>,
+    orbit_distance_lock: Option<f32>,
+    position_limit: Option<f32>,
     position: Vector3<f32>,
+    smoothing_enabled: bool,
+    smooth_alpha: f32,
+    smooth_position: Vector3<f32>,
     pitch: Rad<f32>,
     yaw: Rad<f32>,
+    roll: Rad<f32>,
 }
 
-#[derive(Debug)]
+// animates from one camera to another instead of jumping
+#[derive(Debug, Clone)]
+pub struct CameraTransition {
+    from: Camera,
+    to: Camera,
+    elapsed: Duration,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 enum LockYawMode {
     None,
     Inwards,
@@ -22,9 +60,159 @@ enum LockYawMode {
     Left,
 }
 
+#[derive(Debug, Clone, Copy)]
+pub enum SpeedMode {
+    Exponential,
+    Linear(f32),
+}
+
+// the camera pose and settings as plain values, e.g. for the bookmark file
+#[derive(Debug, Clone, PartialEq)]
+pub struct CameraSnapshot {
+    pub position: [f32; 3],
+    pub pitch: f32,
+    pub yaw: f32,
+    pub roll: f32,
+    pub movement_per_second: f32,
+    pub lock_yaw_mode: String,
+    pub lock_pitch: bool,
+}
+
+impl From<&Camera> for CameraSnapshot {
+    fn from(camera: &Camera) -> Self {
+        Self {
+            position: camera.position.into(),
+            pitch: camera.pitch.0,
+            yaw: camera.yaw.0,
+            roll: camera.roll.0,
+            movement_per_second: camera.movement_per_second,
+            lock_yaw_mode: camera.lock_yaw_mode.name().to_owned(),
+            lock_pitch: camera.lock_pitch,
+        }
+    }
+}
+
+impl CameraSnapshot {
+    pub const BOOKMARK_PATH: &'static str = "camera_bookmark.toml";
+
+    // one `<field> = <value>` line per field, floats are written so that they parse back exactly
+    pub fn to_bookmark(&self) -> String {
+        let Self {
+            position: [x, y, z],
+            pitch,
+            yaw,
+            roll,
+            movement_per_second,
+            lock_yaw_mode,
+            lock_pitch,
+        } = self;
+        format!(
+            "position = [{x}, {y}, {z}]\n\
+            pitch = {pitch}\n\
+            yaw = {yaw}\n\
+            roll = {roll}\n\
+            movement_per_second = {movement_per_second}\n\
+            lock_yaw_mode = \"{lock_yaw_mode}\"\n\
+            lock_pitch = {lock_pitch}\n"
+        )
+    }
+
+    pub fn parse_bookmark(source: &str) -> Result<Self> {
+        let fields: HashMap<_, _> = parse_key_value_lines(source)?
+            .into_iter()
+            .map(|(_, name, value)| (name, value))
+            .collect();
+        let field = |name: &str| {
+            fields
+                .get(name)
+                .copied()
+                .with_context(|| format!("missing field {name}"))
+        };
+        let number = |name: &str| -> Result<f32> {
+            let value = field(name)?;
+            value
+                .parse()
+                .with_context(|| format!("invalid {name}: {value}"))
+        };
+        let position = field("position")?;
+        let coordinates = position
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .split(',')
+            .map(|coordinate| coordinate.trim().parse())
+            .collect::<Result<Vec<f32>, _>>()
+            .with_context(|| format!("invalid position: {position}"))?;
+        let position = coordinates
+            .try_into()
+            .map_err(|_| anyhow!("position needs three coordinates: {position}"))?;
+        let lock_pitch = field("lock_pitch")?;
+        Ok(Self {
+            position,
+            pitch: number("pitch")?,
+            yaw: number("yaw")?,
+            roll: number("roll")?,
+            movement_per_second: number("movement_per_second")?,
+            lock_yaw_mode: field("lock_yaw_mode")?.trim_matches('"').to_owned(),
+            lock_pitch: lock_pitch
+                .parse()
+                .with_context(|| format!("invalid lock_pitch: {lock_pitch}"))?,
+        })
+    }
+}
+
+impl CameraTransition {
+    const DURATION: Duration = Duration::from_millis(500);
+
+    pub fn start(from: Camera, to: Camera) -> Self {
+        Self {
+            from,
+            to,
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    pub fn update(&mut self, delta_time: Duration) -> Camera {
+        self.elapsed = (self.elapsed + delta_time).min(Self::DURATION);
+        let alpha = self.elapsed.as_secs_f32() / Self::DURATION.as_secs_f32();
+        self.from.interpolate_to(&self.to, alpha)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= Self::DURATION
+    }
+}
+
+impl LockYawMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Inwards => "inwards",
+            Self::Right => "right",
+            Self::Outwards => "outwards",
+            Self::Left => "left",
+        }
+    }
+}
+
 impl Camera {
     fn position_matrix(&self) -> Matrix4<f32> {
-        Matrix4::from_translation(self.position)
+        Matrix4::from_translation(self.smoothed_position())
+    }
+
+    pub fn smoothed_position(&self) -> Vector3<f32> {
+        if self.smoothing_enabled {
+            self.smooth_position
+        } else {
+            self.position
+        }
+    }
+
+    pub fn toggle_smoothing(&mut self) {
+        self.smoothing_enabled = !self.smoothing_enabled;
+    }
+
+    pub fn set_smooth_alpha(&mut self, alpha: f32) {
+        self.smooth_alpha = alpha.clamp(0.0, 1.0);
     }
 
     fn pitch_matrix(&self) -> Matrix4<f32> {
@@ -35,8 +223,12 @@ impl Camera {
         Matrix4::from_angle_y(self.yaw)
     }
 
+    fn roll_matrix(&self) -> Matrix4<f32> {
+        Matrix4::from_angle_z(self.roll)
+    }
+
     fn rotation_matrix(&self) -> Matrix4<f32> {
-        self.yaw_matrix() * self.pitch_matrix()
+        self.yaw_matrix() * self.pitch_matrix() * self.roll_matrix()
     }
 
     pub fn to_matrix(&self) -> Matrix4<f32> {
@@ -44,38 +236,223 @@ impl Camera {
     }
 
     const ROTATION_PER_SECOND: Rad<f32> = Rad(0.5);
+    const FINE_MOVEMENT_FACTOR: f32 = 0.1;
+
+    // keeps rounding errors at exactly ±90° from flipping the heading
+    const POLE_TOLERANCE: f32 = 1e-5;
+
+    // past the poles the view is upside down, so the horizontal directions point the other way
+    fn heading_matrix(&self) -> Matrix4<f32> {
+        if self.pitch.0.abs() > FRAC_PI_2 + Self::POLE_TOLERANCE {
+            Matrix4::from_angle_y(self.yaw + Rad::turn_div_2())
+        } else {
+            self.yaw_matrix()
+        }
+    }
 
     fn forward(&self) -> Vector3<f32> {
-        self.yaw_matrix().z.truncate()
+        self.heading_matrix().z.truncate()
+    }
+
+    // along the orbit circle in the orbiting direction, falling back to forward on its axis
+    fn orbital_forward(&self) -> Vector3<f32> {
+        let radial = self.position - self.orbit_target;
+        let radial = Vector3::new(radial.x, 0.0, radial.z);
+        if radial.is_zero() {
+            return self.forward();
+        }
+        self.up().cross(radial).normalize() * self.orbit_angle_per_second.0.signum()
     }
 
     fn right(&self) -> Vector3<f32> {
-        self.yaw_matrix().x.truncate()
+        self.heading_matrix().x.truncate()
     }
 
     fn up(&self) -> Vector3<f32> {
         Vector3::unit_y()
     }
 
+    pub fn set_position_spherical(&mut self, radius: f32, polar: Rad<f32>, azimuth: Rad<f32>) {
+        let (polar_sin, polar_cos) = polar.sin_cos();
+        let (azimuth_sin, azimuth_cos) = azimuth.sin_cos();
+        self.position =
+            radius * Vector3::new(polar_sin * azimuth_sin, polar_cos, polar_sin * azimuth_cos);
+    }
+
+    pub fn apply_preset(&mut self, preset: FractalPreset) {
+        self.position = preset.values().camera_position;
+        self.look_at(Vector3::zero());
+    }
+
+    pub fn look_at(&mut self, target: Vector3<f32>) {
+        let offset = self.position - target;
+        self.update_yaw(Rad::atan2(offset.x, offset.z) - Rad::full_turn() / 2.0);
+        let radius = Vector2::new(offset.x, offset.z).magnitude();
+        self.update_pitch(Rad::atan2(offset.y, radius));
+    }
+
+    // blends the pose, the other settings are taken from the target right away
+    pub fn interpolate_to(&self, target: &Camera, alpha: f32) -> Camera {
+        let alpha = alpha.clamp(0.0, 1.0);
+        // the shorter way can end outside the range of the target angles, e.g. at 190° for -170°
+        if alpha == 1.0 {
+            return target.clone();
+        }
+        let mut camera = target.clone();
+        camera.position = self.position.lerp(target.position, alpha);
+        camera.smooth_position = self.smooth_position.lerp(target.smooth_position, alpha);
+        camera.pitch = Self::interpolate_angle(self.pitch, target.pitch, alpha);
+        camera.yaw = Self::interpolate_angle(self.yaw, target.yaw, alpha);
+        camera.roll = Self::interpolate_angle(self.roll, target.roll, alpha);
+        camera
+    }
+
+    // takes the shorter way around, e.g. 20° instead of 340°
+    fn interpolate_angle(from: Rad<f32>, to: Rad<f32>, alpha: f32) -> Rad<f32> {
+        from + (to - from).normalize_signed() * alpha
+    }
+
+    pub fn restore_from_snapshot(&mut self, snapshot: &CameraSnapshot) {
+        self.position = snapshot.position.into();
+        self.smooth_position = self.position;
+        self.update_pitch(Rad(snapshot.pitch));
+        self.update_yaw(Rad(snapshot.yaw));
+        self.update_roll(Rad(snapshot.roll));
+        self.movement_per_second = snapshot.movement_per_second;
+        self.set_lock_yaw_mode_by_name(&snapshot.lock_yaw_mode);
+        self.set_lock_pitch(snapshot.lock_pitch);
+    }
+
+    const NUM_LOCK_YAW_MODES: usize = 5;
+
+    // unknown names cycle through all modes once, leaving the mode unchanged
+    fn set_lock_yaw_mode_by_name(&mut self, name: &str) {
+        for _ in 0..Self::NUM_LOCK_YAW_MODES {
+            if self.lock_yaw_mode.name() == name {
+                return;
+            }
+            self.cycle_lock_yaw_mode(false);
+        }
+    }
+
+    // inverse of set_position_spherical
+    pub fn position_spherical(&self) -> (f32, Rad<f32>, Rad<f32>) {
+        let radius = self.position.magnitude();
+        if radius == 0.0 {
+            return (radius, Rad::zero(), Rad::zero());
+        }
+        let polar = Rad::acos(self.position.y / radius);
+        let azimuth = Rad::atan2(self.position.x, self.position.z);
+        (radius, polar, azimuth)
+    }
+
+    const LINEAR_SPEED_FACTOR: f32 = 0.1;
+    const MIN_LINEAR_SPEED: f32 = 0.001;
+    const MAX_LINEAR_SPEED: f32 = 100.0;
+
     pub fn update_speed(&mut self, delta: f32) {
-        self.movement_per_second *= (delta * 0.1).exp();
+        match self.speed_mode {
+            SpeedMode::Exponential => self.movement_per_second *= (delta * 0.1).exp(),
+            SpeedMode::Linear(factor) => {
+                self.movement_per_second = clamp(
+                    self.movement_per_second + delta * factor,
+                    Self::MIN_LINEAR_SPEED,
+                    Self::MAX_LINEAR_SPEED,
+                )
+            }
+        }
+    }
+
+    const VERTICAL_SPEED_MULTIPLIER_RANGE: (f32, f32) = (0.0, 2.0);
+
+    pub fn set_vertical_speed_multiplier(&mut self, multiplier: f32) {
+        let (min, max) = Self::VERTICAL_SPEED_MULTIPLIER_RANGE;
+        self.vertical_speed_multiplier = clamp(multiplier, min, max);
+    }
+
+    pub fn update_vertical_speed_multiplier(&mut self, delta: f32) {
+        self.set_vertical_speed_multiplier(self.vertical_speed_multiplier + delta);
+    }
+
+    pub fn toggle_fine_movement(&mut self) {
+        self.fine_movement = !self.fine_movement;
+    }
+
+    pub fn toggle_speed_mode(&mut self) {
+        self.speed_mode = match self.speed_mode {
+            SpeedMode::Exponential => SpeedMode::Linear(Self::LINEAR_SPEED_FACTOR),
+            SpeedMode::Linear(_) => SpeedMode::Exponential,
+        };
+    }
+
+    pub fn movement_per_second(&self) -> f32 {
+        self.movement_per_second
     }
 
     pub fn update_orbit_speed(&mut self, delta: f32) {
-        self.orbit_angle_per_second += Rad(limited_quadratric_delta(
+        self.orbit_angle_per_second += Rad(limited_quadratic_delta(
             self.orbit_angle_per_second.0,
             delta,
         ));
     }
 
+    pub fn orbit_angle_per_second(&self) -> Rad<f32> {
+        self.orbit_angle_per_second
+    }
+
+    pub fn set_orbit_angle_per_second(&mut self, speed: Rad<f32>) {
+        self.orbit_angle_per_second = speed;
+    }
+
     pub fn reset_orbit_speed(&mut self) {
         self.orbit_angle_per_second = Rad::zero();
     }
 
+    pub fn toggle_orbit_distance_lock(&mut self) {
+        self.orbit_distance_lock = match self.orbit_distance_lock {
+            Some(_) => None,
+            None => Some(self.orbit_distance()),
+        };
+    }
+
+    // horizontal, because orbiting only rotates around the vertical axis
+    fn orbit_distance(&self) -> f32 {
+        let offset = self.position - self.orbit_target;
+        Vector2::new(offset.x, offset.z).magnitude()
+    }
+
+    pub fn set_position_limit(&mut self, radius: Option<f32>) {
+        self.position_limit = radius;
+    }
+
+    const POSITION_LIMIT_MARGIN: f32 = 1.5;
+
+    pub fn toggle_position_limit(&mut self) {
+        let radius = match self.position_limit {
+            Some(_) => None,
+            None => Some(self.position.magnitude() * Self::POSITION_LIMIT_MARGIN),
+        };
+        self.set_position_limit(radius);
+    }
+
     pub fn toggle_lock_pitch(&mut self) {
         self.lock_pitch = !self.lock_pitch;
     }
 
+    pub fn set_lock_pitch(&mut self, lock_pitch: bool) {
+        self.lock_pitch = lock_pitch;
+    }
+
+    pub fn toggle_unconstrained_pitch(&mut self) {
+        self.unconstrained_pitch = !self.unconstrained_pitch;
+        // clamps a pitch beyond the poles again
+        self.update_pitch(self.pitch);
+    }
+
+    pub fn toggle_auto_correct_roll(&mut self) {
+        self.auto_correct_roll = !self.auto_correct_roll;
+    }
+
     pub fn cycle_lock_yaw_mode(&mut self, backwards: bool) {
         use LockYawMode::*;
         self.lock_yaw_mode = if backwards {
@@ -97,28 +474,182 @@ impl Camera {
         };
     }
 
+    pub fn status_string(&self) -> String {
+        let Vector3 { x, y, z } = self.position;
+        let radius = self.position.magnitude();
+        let mut status = format!(" | x={x:.3} y={y:.3} z={z:.3} r={radius:.3}");
+        if !matches!(self.lock_yaw_mode, LockYawMode::None) {
+            let name = self.lock_yaw_mode.name();
+            status.push_str(&format!(" [yaw: {name}]"));
+        }
+        if self.lock_pitch {
+            status.push_str(" [pitch locked]");
+        }
+        if self.unconstrained_pitch {
+            status.push_str(" [pitch unconstrained]");
+        }
+        if let Some(radius) = self.position_limit {
+            status.push_str(&format!(" [bounded: {radius:.3}]"));
+        }
+        if let Some(distance) = self.orbit_distance_lock {
+            status.push_str(&format!(" [orbit distance: {distance:.3}]"));
+        }
+        if self.fine_movement {
+            status.push_str(" [fine movement]");
+        }
+        if let SpeedMode::Linear(_) = self.speed_mode {
+            let speed = self.movement_per_second();
+            status.push_str(&format!(" [linear speed: {speed:.3}]"));
+        }
+        if self.vertical_speed_multiplier != 1.0 {
+            let multiplier = self.vertical_speed_multiplier;
+            status.push_str(&format!(" [vertical speed: {multiplier:.2}x]"));
+        }
+        let Rad(orbit_speed) = self.orbit_angle_per_second();
+        if orbit_speed != 0.0 {
+            status.push_str(&format!(" [orbit: {orbit_speed:.3} rad/s]"));
+        }
+        status
+    }
+
+    // longer frames, like after the app was suspended, would teleport the camera
+    pub const MAX_DELTA_TIME: Duration = Duration::from_millis(100);
+
     pub fn update(&mut self, keys: HeldKeys, delta_time: Duration) {
-        let seconds = delta_time.as_secs_f32();
+        let seconds = delta_time.min(Self::MAX_DELTA_TIME).as_secs_f32();
         self.do_movement(keys, seconds);
         self.do_orbit(seconds);
+        self.do_lock_orbit_distance();
+        self.do_position_limit(seconds);
         self.do_lock_rotation();
+        self.do_auto_correct_roll();
+        self.do_smoothing(seconds);
+    }
+
+    // the smooth alpha is the weight of the new position per frame at this rate
+    const SMOOTHING_REFERENCE_FPS: f32 = 60.0;
+
+    fn do_smoothing(&mut self, seconds: f32) {
+        if !self.smoothing_enabled {
+            // start from the current position once smoothing gets enabled
+            self.smooth_position = self.position;
+            return;
+        }
+        // two short frames smooth as much as one frame of the same total length
+        let frames = seconds * Self::SMOOTHING_REFERENCE_FPS;
+        let alpha = 1.0 - (1.0 - self.smooth_alpha).powf(frames);
+        self.smooth_position = alpha * self.position + (1.0 - alpha) * self.smooth_position;
     }
 
+    const MIN_ORBIT_DISTANCE: f32 = 0.001;
+
+    // per second, in fractions of the full movement speed
+    const MOVEMENT_ACCELERATION: f32 = 4.0;
+    const MOVEMENT_DECELERATION: f32 = 8.0;
+
     fn do_movement(&mut self, keys: HeldKeys, seconds: f32) {
-        let movement = self.forward() * keys.forward_magnitude().into()
+        let mut movement_per_second = self.movement_per_second;
+        if keys.is_alt_pressed() || self.fine_movement {
+            movement_per_second *= Self::FINE_MOVEMENT_FACTOR;
+        }
+        let mut forward_magnitude: f32 = keys.forward_magnitude().into();
+        if let Some(distance) = &mut self.orbit_distance_lock {
+            // moving forward approaches the orbit target instead of leaving the locked distance
+            *distance = (*distance - forward_magnitude * movement_per_second * seconds)
+                .max(Self::MIN_ORBIT_DISTANCE);
+            forward_magnitude = 0.0;
+        }
+        // moving forward while orbiting speeds the orbit up instead of leaving it
+        let forward = if self.orbit_angle_per_second.is_zero() {
+            self.forward()
+        } else {
+            self.orbital_forward()
+        };
+        let movement = forward * forward_magnitude
             + self.right() * keys.right_magnitude().into()
             + self.up() * keys.up_magnitude().into();
-        if !movement.is_zero() {
-            self.position += movement.normalize_to(self.movement_per_second * seconds);
+        if movement.is_zero() {
+            self.movement_velocity =
+                (self.movement_velocity - Self::MOVEMENT_DECELERATION * seconds).max(0.0);
+        } else {
+            self.movement_velocity =
+                (self.movement_velocity + Self::MOVEMENT_ACCELERATION * seconds).min(1.0);
+            self.movement_direction = movement.normalize();
+        }
+        if self.movement_velocity > 0.0 {
+            let mut movement =
+                self.movement_direction * (movement_per_second * self.movement_velocity * seconds);
+            // scaled after normalizing, so purely vertical movement slows down too
+            movement.y *= self.vertical_speed_multiplier;
+            self.position += movement;
         }
         let rotation_magnitude = Self::ROTATION_PER_SECOND * seconds;
+        let (previous_pitch, previous_yaw) = (self.pitch, self.yaw);
         self.add_pitch(rotation_magnitude * keys.pitch_magnitude().into());
         self.add_yaw(rotation_magnitude * keys.yaw_magnitude().into());
+        self.add_roll(rotation_magnitude * keys.roll_magnitude().into());
+        if keys.is_control_pressed() {
+            if keys.pitch_magnitude() != 0 {
+                self.pitch = Self::snap_angle(previous_pitch, self.pitch);
+            }
+            if keys.yaw_magnitude() != 0 {
+                self.yaw = Self::snap_angle(previous_yaw, self.yaw);
+            }
+        }
+    }
+
+    const SNAP_STEP: Rad<f32> = Rad(FRAC_PI_4);
+    const SNAP_THRESHOLD: Rad<f32> = Rad(0.05);
+
+    // only snaps when getting close to a step, so holding the key rotates past it again
+    fn snap_angle(previous: Rad<f32>, angle: Rad<f32>) -> Rad<f32> {
+        let nearest = Self::SNAP_STEP * (angle / Self::SNAP_STEP).round();
+        let is_near = |angle: Rad<f32>| (angle - nearest).0.abs() < Self::SNAP_THRESHOLD.0;
+        if is_near(angle) && !is_near(previous) {
+            nearest
+        } else {
+            angle
+        }
+    }
+
+    pub fn orbit_target(&self) -> Vector3<f32> {
+        self.orbit_target
     }
 
     fn do_orbit(&mut self, seconds: f32) {
         let rotation = Matrix3::from_angle_y(self.orbit_angle_per_second * seconds);
-        self.position = rotation * self.position;
+        self.position = self.orbit_target + rotation * (self.position - self.orbit_target);
+    }
+
+    fn do_lock_orbit_distance(&mut self) {
+        let Some(distance) = self.orbit_distance_lock else {
+            return;
+        };
+        let offset = self.position - self.orbit_target;
+        let xz = Vector2::new(offset.x, offset.z);
+        if xz.is_zero() {
+            return;
+        }
+        let xz = xz.normalize_to(distance);
+        self.position = self.orbit_target + Vector3::new(xz.x, offset.y, xz.y);
+    }
+
+    // the fraction of the overshoot that is pulled back per second
+    const POSITION_LIMIT_SPRING_RATE: f32 = 0.1;
+
+    // a soft wall, so the camera can still look beyond it
+    fn do_position_limit(&mut self, seconds: f32) {
+        let Some(limit) = self.position_limit else {
+            return;
+        };
+        let magnitude = self.position.magnitude();
+        if magnitude <= limit {
+            return;
+        }
+        let overshoot = magnitude - limit;
+        self.position -= self
+            .position
+            .normalize_to(overshoot * Self::POSITION_LIMIT_SPRING_RATE * seconds);
     }
 
     fn do_lock_rotation(&mut self) {
@@ -146,11 +677,40 @@ impl Camera {
         self.pitch = Rad::atan2(self.position.y, radius);
     }
 
-    const ROTATION_PER_PIXEL: Rad<f32> = Rad(0.0003);
+    const MAX_ROLL_CORRECTION_PER_FRAME: Rad<f32> = Rad(5.0 * PI / 180.0);
+    const ROLL_CORRECTION_TOLERANCE: Rad<f32> = Rad(0.001);
+
+    fn do_auto_correct_roll(&mut self) {
+        if !self.auto_correct_roll {
+            return;
+        }
+        // yaw and pitch never tilt the horizon, so levelling it means rolling back to zero
+        let correction = clamp(
+            -self.roll,
+            -Self::MAX_ROLL_CORRECTION_PER_FRAME,
+            Self::MAX_ROLL_CORRECTION_PER_FRAME,
+        );
+        self.update_roll(self.roll + correction);
+        // the last steps of the correction would otherwise leave a tiny residual tilt
+        if self.roll.0.abs() < Self::ROLL_CORRECTION_TOLERANCE.0 {
+            self.roll = Rad::zero();
+        }
+    }
+
+    const DEFAULT_ROTATION_PER_PIXEL: Rad<f32> = Rad(0.0003);
+
+    // the sensitivity is given as the cursor movement in pixels for a full turn
+    pub fn from_sensitivity_dpi(sensitivity_pixels_per_turn: f32) -> Rad<f32> {
+        Rad::full_turn() / sensitivity_pixels_per_turn
+    }
+
+    pub fn set_rotation_per_pixel(&mut self, rotation_per_pixel: Rad<f32>) {
+        self.rotation_per_pixel = rotation_per_pixel;
+    }
 
     pub fn rotate_from_cursor_movement(&mut self, yaw_pixels: f32, pitch_pixels: f32) {
-        self.add_pitch(Self::ROTATION_PER_PIXEL * pitch_pixels);
-        self.add_yaw(Self::ROTATION_PER_PIXEL * yaw_pixels);
+        self.add_pitch(self.rotation_per_pixel * pitch_pixels);
+        self.add_yaw(self.rotation_per_pixel * yaw_pixels);
     }
 
     const MAX_PITCH: Rad<f32> = Rad(FRAC_PI_2);
@@ -164,25 +724,380 @@ impl Camera {
         self.update_yaw(self.yaw + yaw);
     }
 
+    fn add_roll(&mut self, roll: Rad<f32>) {
+        self.update_roll(self.roll + roll);
+    }
+
     fn update_pitch(&mut self, pitch: Rad<f32>) {
-        self.pitch = clamp(pitch, Self::MIN_PITCH, Self::MAX_PITCH);
+        self.pitch = if self.unconstrained_pitch {
+            pitch.normalize_signed()
+        } else {
+            clamp(pitch, Self::MIN_PITCH, Self::MAX_PITCH)
+        };
     }
 
     fn update_yaw(&mut self, yaw: Rad<f32>) {
         self.yaw = yaw % Rad::full_turn();
     }
+
+    // unlike yaw, roll is kept within [-π, π] so that auto correction takes the shorter way back
+    fn update_roll(&mut self, roll: Rad<f32>) {
+        self.roll = roll.normalize_signed();
+    }
 }
 
 impl Default for Camera {
     fn default() -> Self {
         Self {
             movement_per_second: 1.0,
+            movement_velocity: 0.0,
+            movement_direction: Vector3::zero(),
+            fine_movement: false,
+            vertical_speed_multiplier: 1.0,
+            rotation_per_pixel: Self::DEFAULT_ROTATION_PER_PIXEL,
+            speed_mode: SpeedMode::Exponential,
             orbit_angle_per_second: Rad::zero(),
             lock_pitch: false,
+            unconstrained_pitch: false,
             lock_yaw_mode: LockYawMode::None,
+            auto_correct_roll: false,
+            orbit_target: Vector3::zero(),
+            orbit_distance_lock: None,
+            position_limit: None,
             position: Vector3::new(0.0, 0.0, -1.0),
+            smoothing_enabled: false,
+            smooth_alpha: 0.1,
+            smooth_position: Vector3::new(0.0, 0.0, -1.0),
             pitch: Rad::zero(),
             yaw: Rad::zero(),
+            roll: Rad::zero(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::Deg;
+
+    fn assert_angle_close(actual: Rad<f32>, expected: impl Into<Rad<f32>>) {
+        let expected = expected.into();
+        let difference = (actual - expected).normalize_signed();
+        assert!(
+            difference.0.abs() < 1e-4,
+            "expected {expected:?}, got {actual:?}"
+        );
+    }
+
+    fn camera_with_yaw(yaw: impl Into<Rad<f32>>) -> Camera {
+        let mut camera = Camera::default();
+        camera.update_yaw(yaw.into());
+        camera
+    }
+
+    #[test]
+    fn interpolation_takes_the_shorter_way_across_half_a_turn() {
+        let from = camera_with_yaw(Deg(170.0));
+        let to = camera_with_yaw(Deg(-170.0));
+        assert_angle_close(from.interpolate_to(&to, 0.25).yaw, Deg(175.0));
+        assert_angle_close(from.interpolate_to(&to, 0.5).yaw, Deg(180.0));
+        assert_angle_close(from.interpolate_to(&to, 0.75).yaw, Deg(-175.0));
+    }
+
+    #[test]
+    fn interpolation_returns_the_endpoints_exactly() {
+        let mut from = camera_with_yaw(Deg(170.0));
+        from.position = Vector3::new(1.0, 2.0, 3.0);
+        let mut to = camera_with_yaw(Deg(-170.0));
+        to.position = Vector3::new(-3.0, 0.5, 7.0);
+        let start = from.interpolate_to(&to, 0.0);
+        assert_eq!(start.yaw, from.yaw);
+        assert_eq!(start.position, from.position);
+        let end = from.interpolate_to(&to, 1.0);
+        assert_eq!(end.yaw, to.yaw);
+        assert_eq!(end.position, to.position);
+    }
+
+    #[test]
+    fn roll_wraps_after_many_turns() {
+        let mut camera = Camera::default();
+        let steps = 1000;
+        for _ in 0..steps {
+            camera.add_roll(Rad::full_turn() * 10.0 / steps as f32);
         }
+        assert_angle_close(camera.roll, Rad(0.0));
+        assert!(camera.roll.0.abs() <= PI);
+    }
+
+    #[test]
+    fn roll_correction_snaps_to_zero_within_tolerance() {
+        let mut camera = Camera::default();
+        camera.toggle_auto_correct_roll();
+        // one full correction step leaves a residual of 0.0005
+        camera.roll = Camera::MAX_ROLL_CORRECTION_PER_FRAME + Rad(0.0005);
+        camera.do_auto_correct_roll();
+        assert_eq!(camera.roll, Rad(0.0));
+    }
+
+    #[test]
+    fn roll_correction_is_limited_per_frame() {
+        let mut camera = Camera::default();
+        camera.toggle_auto_correct_roll();
+        camera.roll = Rad(1.0);
+        camera.do_auto_correct_roll();
+        assert_angle_close(
+            camera.roll,
+            Rad(1.0) - Camera::MAX_ROLL_CORRECTION_PER_FRAME,
+        );
+    }
+
+    fn assert_vector_close(actual: Vector3<f32>, expected: Vector3<f32>) {
+        assert!(
+            (actual - expected).magnitude() < 1e-4,
+            "expected {expected:?}, got {actual:?}"
+        );
+    }
+
+    fn unconstrained_camera_with_pitch(pitch: impl Into<Rad<f32>>) -> Camera {
+        let mut camera = Camera::default();
+        camera.toggle_unconstrained_pitch();
+        camera.update_pitch(pitch.into());
+        camera
+    }
+
+    #[test]
+    fn constrained_pitch_stops_at_the_poles() {
+        let mut camera = Camera::default();
+        camera.add_pitch(Deg(120.0).into());
+        assert_eq!(camera.pitch, Camera::MAX_PITCH);
+        camera.add_pitch(Deg(-300.0).into());
+        assert_eq!(camera.pitch, Camera::MIN_PITCH);
+    }
+
+    #[test]
+    fn heading_does_not_flip_exactly_at_the_poles() {
+        for pitch in [Deg(90.0), Deg(-90.0)] {
+            let camera = unconstrained_camera_with_pitch(pitch);
+            assert_vector_close(camera.forward(), Vector3::unit_z());
+            assert_vector_close(camera.right(), Vector3::unit_x());
+        }
+    }
+
+    #[test]
+    fn heading_flips_past_the_poles() {
+        for pitch in [Deg(100.0), Deg(-100.0), Deg(179.0)] {
+            let camera = unconstrained_camera_with_pitch(pitch);
+            assert_vector_close(camera.forward(), -Vector3::unit_z());
+            assert_vector_close(camera.right(), -Vector3::unit_x());
+        }
+    }
+
+    #[test]
+    fn unconstrained_pitch_turns_over_the_poles() {
+        let mut camera = unconstrained_camera_with_pitch(Deg(80.0));
+        camera.add_pitch(Deg(20.0).into());
+        assert_angle_close(camera.pitch, Deg(100.0));
+        camera.add_pitch(Deg(90.0).into());
+        assert_angle_close(camera.pitch, Deg(-170.0));
+        assert_vector_close(camera.forward(), -Vector3::unit_z());
+    }
+
+    #[test]
+    fn constraining_the_pitch_again_clamps_it() {
+        let mut camera = unconstrained_camera_with_pitch(Deg(100.0));
+        camera.toggle_unconstrained_pitch();
+        assert_eq!(camera.pitch, Camera::MAX_PITCH);
+        assert_vector_close(camera.forward(), Vector3::unit_z());
+    }
+
+    fn camera_for_snapshot() -> Camera {
+        let mut camera = Camera {
+            position: Vector3::new(0.1, -2.5, 1.0 / 3.0),
+            movement_per_second: 0.125,
+            ..Default::default()
+        };
+        camera.update_pitch(Rad(0.3));
+        camera.update_yaw(Rad(-2.0));
+        camera.update_roll(Rad(1.0 / 7.0));
+        camera
+    }
+
+    #[test]
+    fn bookmark_round_trips_exactly() {
+        let snapshot = CameraSnapshot::from(&camera_for_snapshot());
+        let parsed = CameraSnapshot::parse_bookmark(&snapshot.to_bookmark()).unwrap();
+        assert_eq!(parsed, snapshot);
+    }
+
+    #[test]
+    fn snapshot_restores_the_camera() {
+        let camera = camera_for_snapshot();
+        let mut restored = Camera::default();
+        restored.restore_from_snapshot(&CameraSnapshot::from(&camera));
+        assert_eq!(restored.position, camera.position);
+        assert_eq!(restored.smoothed_position(), camera.position);
+        assert_eq!(restored.pitch, camera.pitch);
+        assert_eq!(restored.yaw, camera.yaw);
+        assert_eq!(restored.roll, camera.roll);
+        assert_eq!(restored.movement_per_second, camera.movement_per_second);
+    }
+
+    #[test]
+    fn bookmark_ignores_comments_and_field_order() {
+        let source = "# stored camera\n\
+            lock_pitch = true\n\
+            lock_yaw_mode = \"left\"\n\
+            movement_per_second = 2\n\
+            roll = 0\n\
+            yaw = 1.5 # radians\n\
+            pitch = -0.5\n\
+            position = [1, 2, 3]\n";
+        let snapshot = CameraSnapshot::parse_bookmark(source).unwrap();
+        assert_eq!(snapshot.position, [1.0, 2.0, 3.0]);
+        assert_eq!(snapshot.yaw, 1.5);
+        assert_eq!(snapshot.lock_yaw_mode, "left");
+        assert!(snapshot.lock_pitch);
+    }
+
+    #[test]
+    fn bookmark_rejects_missing_and_invalid_fields() {
+        let bookmark = CameraSnapshot::from(&Camera::default()).to_bookmark();
+        let without_yaw: String = bookmark
+            .lines()
+            .filter(|line| !line.starts_with("yaw"))
+            .map(|line| format!("{line}\n"))
+            .collect();
+        assert!(CameraSnapshot::parse_bookmark(&without_yaw).is_err());
+        let short_position = bookmark.replace("position = [0, 0, -1]", "position = [0, 0]");
+        assert_ne!(short_position, bookmark);
+        assert!(CameraSnapshot::parse_bookmark(&short_position).is_err());
+        assert!(CameraSnapshot::parse_bookmark("pitch").is_err());
+    }
+
+    #[test]
+    fn bookmark_round_trips_every_lock_yaw_mode() {
+        let mut camera = Camera::default();
+        for _ in 0..Camera::NUM_LOCK_YAW_MODES {
+            camera.cycle_lock_yaw_mode(false);
+            camera.toggle_lock_pitch();
+            let bookmark = CameraSnapshot::from(&camera).to_bookmark();
+            let snapshot = CameraSnapshot::parse_bookmark(&bookmark).unwrap();
+            let mut restored = Camera::default();
+            restored.restore_from_snapshot(&snapshot);
+            assert_eq!(restored.lock_yaw_mode, camera.lock_yaw_mode);
+            assert_eq!(restored.lock_pitch, camera.lock_pitch);
+        }
+    }
+
+    #[test]
+    fn unknown_lock_yaw_mode_is_left_unchanged() {
+        let mut camera = Camera::default();
+        camera.cycle_lock_yaw_mode(false);
+        let snapshot = CameraSnapshot {
+            lock_yaw_mode: "sideways".to_owned(),
+            ..CameraSnapshot::from(&Camera::default())
+        };
+        camera.restore_from_snapshot(&snapshot);
+        assert_eq!(camera.lock_yaw_mode, LockYawMode::Inwards);
+    }
+
+    fn distance_moved_forward(delta_time: Duration) -> f32 {
+        let mut camera = Camera::default();
+        let start = camera.position;
+        camera.update(HeldKeys::MoveForward, delta_time);
+        (camera.position - start).magnitude()
+    }
+
+    #[test]
+    fn long_frames_move_like_the_capped_delta_time() {
+        let capped = distance_moved_forward(Camera::MAX_DELTA_TIME);
+        assert!(capped > 0.0);
+        assert_eq!(distance_moved_forward(Duration::from_secs(5)), capped);
+    }
+
+    #[test]
+    fn long_frames_move_at_most_the_capped_distance() {
+        let max_distance =
+            Camera::default().movement_per_second * Camera::MAX_DELTA_TIME.as_secs_f32();
+        assert!(distance_moved_forward(Duration::from_secs(10)) <= max_distance);
+    }
+
+    fn smoothing_camera(alpha: f32) -> Camera {
+        let mut camera = Camera::default();
+        camera.toggle_smoothing();
+        camera.set_smooth_alpha(alpha);
+        camera.smooth_position = Vector3::zero();
+        camera.position = Vector3::new(1.0, 0.0, 0.0);
+        camera
+    }
+
+    #[test]
+    fn smoothing_with_alpha_one_follows_the_position() {
+        let mut camera = smoothing_camera(1.0);
+        camera.do_smoothing(1.0 / 60.0);
+        assert_vector_close(camera.smoothed_position(), camera.position);
+    }
+
+    #[test]
+    fn smoothing_applies_the_alpha_per_reference_frame() {
+        let mut camera = smoothing_camera(0.25);
+        camera.do_smoothing(1.0 / Camera::SMOOTHING_REFERENCE_FPS);
+        assert_vector_close(camera.smoothed_position(), Vector3::new(0.25, 0.0, 0.0));
+    }
+
+    #[test]
+    fn smoothing_does_not_depend_on_the_frame_rate() {
+        let mut slow = smoothing_camera(0.1);
+        slow.do_smoothing(1.0 / 30.0);
+        let mut fast = smoothing_camera(0.1);
+        fast.do_smoothing(1.0 / 60.0);
+        fast.do_smoothing(1.0 / 60.0);
+        assert_vector_close(slow.smoothed_position(), fast.smoothed_position());
+    }
+
+    fn assert_spherical_round_trip(
+        radius: f32,
+        polar: impl Into<Rad<f32>>,
+        azimuth: impl Into<Rad<f32>>,
+    ) {
+        let (polar, azimuth) = (polar.into(), azimuth.into());
+        let mut camera = Camera::default();
+        camera.set_position_spherical(radius, polar, azimuth);
+        let (actual_radius, actual_polar, actual_azimuth) = camera.position_spherical();
+        assert!(
+            (actual_radius - radius).abs() < 1e-5,
+            "expected radius {radius}, got {actual_radius}"
+        );
+        assert_angle_close(actual_polar, polar);
+        assert_angle_close(actual_azimuth, azimuth);
+    }
+
+    #[test]
+    fn spherical_position_round_trips() {
+        assert_spherical_round_trip(2.0, Deg(60.0), Deg(30.0));
+        assert_spherical_round_trip(0.5, Deg(120.0), Deg(-150.0));
+        assert_spherical_round_trip(10.0, Deg(90.0), Deg(90.0));
+    }
+
+    #[test]
+    fn spherical_position_at_the_origin_is_zero() {
+        let camera = Camera {
+            position: Vector3::zero(),
+            ..Default::default()
+        };
+        assert_eq!(camera.position_spherical(), (0.0, Rad::zero(), Rad::zero()));
+    }
+
+    #[test]
+    fn spherical_position_of_the_poles_has_no_azimuth() {
+        let mut camera = Camera {
+            position: Vector3::new(0.0, 3.0, 0.0),
+            ..Default::default()
+        };
+        assert_eq!(camera.position_spherical(), (3.0, Rad::zero(), Rad::zero()));
+        camera.position = Vector3::new(0.0, -3.0, 0.0);
+        let (radius, polar, _) = camera.position_spherical();
+        assert_eq!(radius, 3.0);
+        assert_angle_close(polar, Deg(180.0));
     }
 }
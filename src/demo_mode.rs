@@ -0,0 +1,49 @@
+use crate::{camera::Camera, parameters::Parameters};
+use cgmath::{Rad, Vector3, Zero};
+use std::{
+    f32::consts::{FRAC_PI_2, TAU},
+    time::Duration,
+};
+
+#[derive(Debug)]
+pub struct DemoMode {
+    time: f32,
+    start_scene_index: u32,
+    // the orbit starts behind the camera, so it only jumps in distance and height
+    start_azimuth: Rad<f32>,
+}
+
+impl DemoMode {
+    const RADIUS: f32 = 2.0;
+    const ORBIT_ANGLE_PER_SECOND: Rad<f32> = Rad(0.1);
+    // from the equator to overhead and back
+    const SWEEP_SECONDS: f32 = 120.0;
+    // the yaw is undefined directly overhead
+    const MIN_POLAR: Rad<f32> = Rad(0.05);
+    const SECONDS_PER_SCENE: f32 = 30.0;
+
+    pub fn start(camera: &Camera, parameters: &Parameters) -> Self {
+        let (_, _, start_azimuth) = camera.position_spherical();
+        Self {
+            time: 0.0,
+            start_scene_index: parameters.scene_index(),
+            start_azimuth,
+        }
+    }
+
+    pub fn update(
+        &mut self,
+        camera: &mut Camera,
+        parameters: &mut Parameters,
+        delta_time: Duration,
+    ) {
+        self.time += delta_time.as_secs_f32();
+        let sweep = (1.0 - (TAU * self.time / Self::SWEEP_SECONDS).cos()) / 2.0;
+        let polar = Rad(FRAC_PI_2) + (Self::MIN_POLAR - Rad(FRAC_PI_2)) * sweep;
+        let azimuth = self.start_azimuth + Self::ORBIT_ANGLE_PER_SECOND * self.time;
+        camera.set_position_spherical(Self::RADIUS, polar, azimuth);
+        camera.look_at(Vector3::zero());
+        let scenes_shown = (self.time / Self::SECONDS_PER_SCENE) as usize;
+        parameters.set_scene_index(self.start_scene_index as usize + scenes_shown);
+    }
+}
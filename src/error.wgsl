@@ -0,0 +1,6 @@
+const ERROR_COLOR = vec4<f32>(0.8, 0.1, 0.1, 1);
+
+@fragment
+fn fragment_main() -> @location(0) vec4<f32> {
+    return ERROR_COLOR;
+}
@@ -0,0 +1,7 @@
+// appended to fragment.wgsl when push constants are available
+
+var<push_constant> fast_parameters_push_constant: FastParameters;
+
+fn load_fast_parameters() -> FastParameters {
+    return fast_parameters_push_constant;
+}
@@ -0,0 +1,5 @@
+// appended to fragment.wgsl when push constants are not available
+
+fn load_fast_parameters() -> FastParameters {
+    return FastParameters(parameters.camera_matrix, parameters.time);
+}
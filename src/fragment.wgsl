@@ -5,7 +5,6 @@ const MAX_ITERATIONS = u32(5.0e3);
 const FOV_DEGREES = 90;
 
 // colors / shading
-const BACKGROUND_COLOR = Color(0, 0, 0);
 const SUN_DIRECTION = Direction(-1, -0.5, 1);
 const SUN_COLOR = Color(1, 1, 1);
 const SHADOW_FACTOR = 0.7;
@@ -15,8 +14,12 @@ const SPECULAR_SHARPNESS = 16;
 const AMBIENT_OCCLUSION_FACTOR = 0.2;
 const AMBIENT_OCCLUSION_SHARPNESS = 100;
 
+// the viewport of the fragment currently being shaded, see fragment_main
+var<private> viewport_index: u32;
+
 fn scene(position: Position) -> Object {
-    switch (parameters.scene_index) {
+    // out of range indices (i.e. the scene after the last one) show the first scene
+    switch (parameters.scene_index + viewport_index) {
         case 0, default: {
             return menger_sponge(position, 1.0 / 6.0, 3.0);
         }
@@ -74,11 +77,35 @@ fn scene(position: Position) -> Object {
         case 18: {
             return mandelbulb(position, animate_between(4, 9), 100.0);
         }
+        case 19: {
+            return mandelbulb(position, parameters.fractal_power, 100.0);
+        }
+        case 20: {
+            let scale = parameters.mandelbox_scale + 0.2 * seeded_variation(0);
+            let fold_radius = parameters.mandelbox_fold_radius + 0.2 * seeded_variation(1);
+            return mandelbox(position, scale, fold_radius);
+        }
     }
 }
 
+fn pcg_hash(input: u32) -> u32 {
+    let state = input * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+// between -1 and 1 for each channel, zero for the default seed so scenes keep their shape
+fn seeded_variation(channel: u32) -> Scalar {
+    if (parameters.seed == 0) {
+        return 0;
+    }
+    let hash = pcg_hash(pcg_hash(parameters.seed) ^ channel);
+    return Scalar(hash) / Scalar(0xffffffffu) * 2 - 1;
+}
+
+// the shapes animate with the secondary time, independent of the rotating colors
 fn animate_between(a: Scalar, b: Scalar) -> Scalar {
-    return a + (b - a) * (0.5 + 0.5 * sin(parameters.time * 0.2));
+    return a + (b - a) * (0.5 + 0.5 * sin(parameters.time_b * 0.2));
 }
 
 alias Scalar = f32;
@@ -111,55 +138,61 @@ struct Parameters {
     time: Scalar,
     num_iterations: u32,
     scene_index: u32,
+    fractal_power: Scalar,
+    dither_enabled: u32,
+    bloom_enabled: u32,
+    bloom_threshold: Scalar,
+    bloom_strength: Scalar,
+    viewport_count: u32,
+    step_factor: Scalar,
+    chromatic_aberration: Scalar,
+    mandelbox_scale: Scalar,
+    mandelbox_fold_radius: Scalar,
+    background_color_top: vec3<Scalar>,
+    background_color_bottom: vec3<Scalar>,
+    interior_mode: u32,
+    orbit_target: vec3<Scalar>,
+    show_orbit_target: u32,
+    normal_epsilon: Scalar,
+    camera_matrix_b: mat4x4<Scalar>,
+    blend_views: Scalar,
+    jitter: vec2<Scalar>,
+    jitter_enabled: u32,
+    wall_time: Scalar,
+    sharpen_strength: Scalar,
+    film_grain: Scalar,
+    seed: u32,
+    debug_mode: u32,
+    letterbox_scale: vec2<Scalar>,
+    scanline_strength: Scalar,
+    ssr_strength: Scalar,
+    time_b: Scalar,
 }
 
 @group(0) @binding(0) var<uniform> parameters: Parameters;
 
-fn colorize(position: Position) -> Color {
-    return min(Color(1), position + 0.5);
-}
-
-fn max_component_2(a: vec2<Scalar>) -> Scalar {
-    return max(a.x, a.y);
-}
-
-fn min_component_2(a: vec2<Scalar>) -> Scalar {
-    return min(a.x, a.y);
-}
-
-fn max_component_3(a: Vector) -> Scalar {
-    return max(max_component_2(a.xy), a.z);
-}
-
-fn min_component_3(a: Vector) -> Scalar {
-    return min(min_component_2(a.xy), a.z);
-}
-
-fn box(position: Position, size: Vector) -> Distance {
-    let q = abs(position) - size;
-    return length(max(q, Vector(0))) + min(max_component_3(q), 0);
-}
-
-fn half_space(position: Position, anchor: Position, normal: Direction) -> Distance {
-    return dot(position - anchor, normal);
+// values that change every frame, load_fast_parameters is appended depending on push constant support
+struct FastParameters {
+    camera_matrix: mat4x4<Scalar>,
+    time: Scalar,
 }
 
-fn plane_normal(a: Position, b: Position, c: Position) -> Direction {
-    return normalize(cross(c - a, b - a));
-}
+var<private> fast_parameters: FastParameters;
 
-fn tetrahedron(position: Position, a: Position, b: Position, c: Position, d: Position) -> Distance {
-    return max(max(max(
-        half_space(position, a, plane_normal(a, b, c)),
-        half_space(position, a, plane_normal(a, c, d))),
-        half_space(position, a, plane_normal(a, d, b))),
-        half_space(position, b, plane_normal(b, d, c)));
+// the hue rotates with the primary time, independent of the animated shapes
+fn colorize(position: Position) -> Color {
+    let color = position + 0.5;
+    if (fast_parameters.time == 0) {
+        return min(Color(1), color);
+    }
+    // Rodrigues' rotation around the gray axis
+    let axis = normalize(Color(1));
+    let angle = fast_parameters.time * 0.2;
+    let rotated = color * cos(angle) + cross(axis, color) * sin(angle) + axis * dot(axis, color) * (1 - cos(angle));
+    return clamp(rotated, Color(0), Color(1));
 }
 
-fn mirror(position: Position, anchor: Position, normal: Direction) -> Position {
-    let distance = dot(position - anchor, normal);
-    return position + (abs(distance) - distance) * normal;
-}
+// #include "sdf_library.wgsl"
 
 fn sierpinski_tetrahedron(position: Position) -> Object {
     const BASE_SCALE_FACTOR = 0.5;
@@ -187,18 +220,6 @@ fn sierpinski_tetrahedron(position: Position) -> Object {
     return object(tetrahedron(p, TOP, a, b, c), colorize(1.5 * position));
 }
 
-fn repeat(position: Position) -> Position {
-    return fract(position + 0.5) - 0.5;
-}
-
-fn cross_inside(position: Position, size: Distance) -> Distance {
-    let p = abs(position);
-    let x = max_component_2(p.yz);
-    let y = max_component_2(p.zx);
-    let z = max_component_2(p.xy);
-    return min_component_3(Position(x, y, z)) - size;
-}
-
 fn menger_sponge(position: Position, cross_size: Scalar, scale_factor: Scalar) -> Object {
     const SIZE = 0.5;
     var distance = box(position, Vector(SIZE));
@@ -276,44 +297,110 @@ struct MarchResult {
     color: Color,
     closeness: Scalar,
     steps: u32,
+    is_interior: bool,
+}
+
+fn background(direction: Direction) -> Color {
+    return mix(parameters.background_color_bottom, parameters.background_color_top, direction.y * 0.5 + 0.5);
+}
+
+fn interior_color(last_color: Color, total_distance: Distance, min_distance: Distance) -> Color {
+    switch (parameters.interior_mode) {
+        case 0, default: {
+            return Color(0);
+        }
+        case 1: {
+            return last_color;
+        }
+        case 2: {
+            // the gradient shows how far the ray got with its steps
+            return Color(1 - exp(-total_distance));
+        }
+        case 3: {
+            // orbit trap on the closest approach to the surface, on a logarithmic scale
+            const ORDERS_OF_MAGNITUDE = 6.0;
+            return Color(1 - clamp(log(min_distance / MIN_DISTANCE) / log(pow(10, ORDERS_OF_MAGNITUDE)), 0, 1));
+        }
+    }
 }
 
 fn march(start_position: Position, direction: Direction) -> MarchResult {
     var result: MarchResult;
     result.position = start_position;
     result.distance = -INFINITY;
-    result.color = BACKGROUND_COLOR;
+    result.color = background(direction);
     var total_distance = Distance(0);
     var closeness = INFINITY;
+    var min_distance = INFINITY;
+    var last_color = result.color;
     var iteration = 0u;
     for (; iteration < MAX_ITERATIONS && total_distance < MAX_TOTAL_DISTANCE; iteration++) {
         let position = start_position + total_distance * direction;
         let object = scene(position);
         closeness = min(closeness, object.distance / total_distance);
+        min_distance = min(min_distance, object.distance);
+        last_color = object.color;
         if (object.distance <= MIN_DISTANCE) {
             result.color = object.color;
             result.distance = total_distance;
             result.position = position;
             break;
         }
-        total_distance += object.distance;
+        // values above 1 march faster, but may step through thin features and cause artifacts
+        total_distance += object.distance * parameters.step_factor;
     }
     result.closeness = closeness;
     result.steps = iteration;
+    // the ray neither hit anything nor escaped before running out of steps
+    result.is_interior = iteration == MAX_ITERATIONS && total_distance < MAX_TOTAL_DISTANCE;
+    if (result.is_interior) {
+        result.color = interior_color(last_color, total_distance, min_distance);
+    }
     return result;
 }
 
-fn calculate_normal(position: Position) -> Direction {
+fn calculate_gradient(position: Position) -> Vector {
     // adapted from here (Tetrahedron technique): https://iquilezles.org/articles/normalsSDF/
     let k = vec2<Scalar>(1, -1);
-    return normalize(k.xyy * scene(position + k.xyy * MIN_DISTANCE).distance +
-                     k.yyx * scene(position + k.yyx * MIN_DISTANCE).distance +
-                     k.yxy * scene(position + k.yxy * MIN_DISTANCE).distance +
-                     k.xxx * scene(position + k.xxx * MIN_DISTANCE).distance);
+    // too large offsets produce faceted normals, too small ones floating point noise
+    let epsilon = parameters.normal_epsilon;
+    // the four samples sum up to four times the directional differences
+    return (k.xyy * scene(position + k.xyy * epsilon).distance +
+            k.yyx * scene(position + k.yyx * epsilon).distance +
+            k.yxy * scene(position + k.yxy * epsilon).distance +
+            k.xxx * scene(position + k.xxx * epsilon).distance) / (4 * epsilon);
+}
+
+fn calculate_normal(position: Position) -> Direction {
+    return normalize(calculate_gradient(position));
+}
+
+fn viewport_aspect_scale() -> vec2<Scalar> {
+    // the viewports split the width, the shorter side of each one spans [-1, 1]
+    let scale = parameters.aspect_scale * vec2(1 / Scalar(parameters.viewport_count), 1);
+    return scale / min_component_2(scale);
+}
+
+fn is_on_orbit_target_cross_hair(camera_position: Position, view_plane_position: vec2<Scalar>, view_plane_distance: Scalar) -> bool {
+    const CROSS_HAIR_SIZE = 0.03;
+    const CROSS_HAIR_THICKNESS = 0.003;
+    // project the target onto the view plane using the camera axes
+    let to_target = parameters.orbit_target - camera_position;
+    let target_depth = dot(to_target, transform_direction(Direction(0, 0, 1)));
+    if (target_depth <= 0) {
+        return false;
+    }
+    let target_view_plane_position = vec2(
+        dot(to_target, transform_direction(Direction(1, 0, 0))),
+        dot(to_target, transform_direction(Direction(0, 1, 0))),
+    ) * view_plane_distance / target_depth;
+    let offset = abs(view_plane_position - target_view_plane_position);
+    return (offset.x < CROSS_HAIR_THICKNESS && offset.y < CROSS_HAIR_SIZE) ||
+           (offset.y < CROSS_HAIR_THICKNESS && offset.x < CROSS_HAIR_SIZE);
 }
 
 fn transform_homogeneous(a: Homogeneous) -> Vector {
-    return (a * parameters.camera_matrix).xyz;
+    return (a * camera_matrix).xyz;
 }
 
 fn transform_position(position: Position) -> Position {
@@ -324,26 +411,131 @@ fn transform_direction(direction: Direction) -> Direction {
     return transform_homogeneous(vec4(direction, 0));
 }
 
+struct FragmentOutput {
+    @location(0) color: vec4<Scalar>,
+    // for the screen-space reflections, see ssr.wgsl
+    @location(1) normal_depth: vec4<Scalar>,
+}
+
 @fragment
-fn fragment_main(@location(0) screen_position: vec2<Scalar>) -> @location(0) vec4<Scalar> {
+fn fragment_main(
+    @location(0) screen_position: vec2<Scalar>,
+    @location(1) @interpolate(flat) fragment_viewport_index: u32,
+) -> FragmentOutput {
+    viewport_index = fragment_viewport_index;
+    fast_parameters = load_fast_parameters();
+    let jitter = Scalar(parameters.jitter_enabled != 0) * parameters.jitter;
+    let view_plane_position = (screen_position + jitter) * viewport_aspect_scale();
+    camera_matrix = fast_parameters.camera_matrix;
+    var color = shade(view_plane_position);
+    // the reflections only follow the main camera
+    let main_normal_depth = normal_depth;
+    if (parameters.blend_views > 0) {
+        camera_matrix = parameters.camera_matrix_b;
+        color = mix(color, shade(view_plane_position), parameters.blend_views);
+    }
+    return FragmentOutput(vec4(color, 1), main_normal_depth);
+}
+
+// the camera currently being rendered from, see fragment_main
+var<private> camera_matrix: mat4x4<Scalar>;
+// the view space normal and depth of the object hit by shade, zero if there is none
+var<private> normal_depth: vec4<Scalar>;
+
+fn shade(view_plane_position: vec2<Scalar>) -> Color {
     const CAMERA_DIRECTION_Z = 1 / atan(FOV_DEGREES * PI / 180);
-    let camera_direction = transform_direction(normalize(Direction(screen_position * parameters.aspect_scale, CAMERA_DIRECTION_Z)));
+    let view_direction = normalize(Direction(view_plane_position, CAMERA_DIRECTION_Z));
+    let camera_direction = transform_direction(view_direction);
     let camera_position = transform_position(Position(0));
     let object_result = march(camera_position, camera_direction);
+    normal_depth = vec4(0);
+    if (parameters.debug_mode != 0) {
+        return debug_color(object_result);
+    }
     var color = object_result.color;
     if (object_result.distance >= 0) {
         let object_position = object_result.position;
         let object_normal = calculate_normal(object_position);
+        // the camera matrix only rotates and translates, so its transpose undoes the rotation
+        let to_view = transpose(mat3x3(camera_matrix[0].xyz, camera_matrix[1].xyz, camera_matrix[2].xyz));
+        let view_depth = distance(camera_position, object_position) * view_direction.z;
+        normal_depth = vec4(to_view * object_normal, view_depth);
         let to_sun = normalize(-SUN_DIRECTION);
         let to_camera = -camera_direction;
         let halfway = normalize(to_camera + to_sun);
         let specular = pow(max(dot(halfway, object_normal), 0), SPECULAR_SHARPNESS);
         let sun_result = march(object_position + object_normal * 2 * MIN_DISTANCE, to_sun);
-        let ambient_occlusion = pow(1 - Scalar(object_result.steps) / Scalar(MAX_ITERATIONS), AMBIENT_OCCLUSION_SHARPNESS);
-        color *= mix(AMBIENT_OCCLUSION_FACTOR, 1, ambient_occlusion);
+        color *= ambient_occlusion(object_result.steps);
         let shadow = Scalar(sun_result.distance < 0) * SHADOW_SHARPNESS * sun_result.closeness;
         color *= mix(SHADOW_FACTOR, 1, clamp(shadow, 0, 1));
         color += SPECULAR_FACTOR * shadow * specular * SUN_COLOR;
     }
-    return vec4(color, 1);
+    if (parameters.show_orbit_target != 0 && is_on_orbit_target_cross_hair(camera_position, view_plane_position, CAMERA_DIRECTION_Z)) {
+        color = Color(1);
+    }
+    return color;
+}
+
+fn ambient_occlusion(steps: u32) -> Scalar {
+    let ambient_occlusion = pow(1 - Scalar(steps) / Scalar(MAX_ITERATIONS), AMBIENT_OCCLUSION_SHARPNESS);
+    return mix(AMBIENT_OCCLUSION_FACTOR, 1, ambient_occlusion);
+}
+
+// blue for few steps over green to red for many
+fn heatmap(fraction: Scalar) -> Color {
+    return Color(fraction, 1 - abs(2 * fraction - 1), 1 - fraction);
+}
+
+fn debug_color(result: MarchResult) -> Color {
+    if (parameters.debug_mode == 1) {
+        return heatmap(Scalar(result.steps) / Scalar(MAX_ITERATIONS));
+    }
+    if (result.distance < 0) {
+        return Color(0);
+    }
+    switch (parameters.debug_mode) {
+        case 2: {
+            return calculate_normal(result.position) * 0.5 + 0.5;
+        }
+        case 3: {
+            // exact distance fields have a gradient magnitude of one (green),
+            // red overestimates the distance and causes overstepping, blue wastes steps
+            let magnitude = length(calculate_gradient(result.position));
+            return clamp(Color(magnitude - 1, 1 - abs(magnitude - 1), 1 - magnitude), Color(0), Color(1));
+        }
+        case 4, default: {
+            return Color(ambient_occlusion(result.steps));
+        }
+    }
+}
+
+fn mandelbox(position: Position, scale: Scalar, fold_radius: Scalar) -> Object {
+    // adapted from http://blog.hvidtfeldts.net/index.php/2011/11/distance-estimated-3d-fractals-vi-the-mandelbox/
+    const ZOOM = 6.0;
+    const MIN_RADIUS_SQUARED = 0.25;
+    let fold_radius_squared = fold_radius * fold_radius;
+    let offset = position * ZOOM;
+    var current = offset;
+    var derivative = 1.0;
+    for (var i = 0u; i <= parameters.num_iterations; i++) {
+        // box fold
+        current = clamp(current, Position(-1), Position(1)) * 2 - current;
+
+        // sphere fold
+        let radius_squared = dot(current, current);
+        if (radius_squared < MIN_RADIUS_SQUARED) {
+            let factor = fold_radius_squared / MIN_RADIUS_SQUARED;
+            current *= factor;
+            derivative *= factor;
+        } else if (radius_squared < fold_radius_squared) {
+            let factor = fold_radius_squared / radius_squared;
+            current *= factor;
+            derivative *= factor;
+        }
+
+        current = scale * current + offset;
+        derivative = derivative * abs(scale) + 1.0;
+    }
+    let distance = length(current) / abs(derivative) / ZOOM;
+    return object(distance, colorize(position));
 }
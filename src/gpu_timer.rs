@@ -0,0 +1,60 @@
+use crate::query_readback::QueryReadback;
+use std::{cell::Cell, time::Duration};
+use wgpu::{CommandEncoder, Device, QueryType, Queue, RenderPassTimestampWrites};
+
+// measures the GPU time from the start of the ray marching pass to the end of the blit pass,
+// like the pipeline statistics the timestamps are read back a few frames late
+#[derive(Debug)]
+pub struct GpuTimer {
+    readback: QueryReadback,
+    // nanoseconds per timestamp tick
+    timestamp_period: f32,
+    // taken by the caller, so every measurement is only seen once
+    frame_duration: Cell<Option<Duration>>,
+}
+
+impl GpuTimer {
+    pub fn init(device: &Device, queue: &Queue) -> Self {
+        Self {
+            readback: QueryReadback::init(device, "gpu_timer", QueryType::Timestamp, 2),
+            timestamp_period: queue.get_timestamp_period(),
+            frame_duration: Cell::new(None),
+        }
+    }
+
+    pub fn start_of_frame(&self) -> RenderPassTimestampWrites<'_> {
+        RenderPassTimestampWrites {
+            query_set: self.readback.query_set(),
+            beginning_of_pass_write_index: Some(0),
+            end_of_pass_write_index: None,
+        }
+    }
+
+    pub fn end_of_frame(&self) -> RenderPassTimestampWrites<'_> {
+        RenderPassTimestampWrites {
+            query_set: self.readback.query_set(),
+            beginning_of_pass_write_index: None,
+            end_of_pass_write_index: Some(1),
+        }
+    }
+
+    // returns whether the queries got resolved, which is skipped while the last readback is pending
+    pub fn resolve(&self, encoder: &mut CommandEncoder) -> bool {
+        if let Some([start, end]) = self.readback.finish_readback::<[u64; 2]>() {
+            let ticks = end.saturating_sub(start);
+            let nanoseconds = ticks as f64 * f64::from(self.timestamp_period);
+            self.frame_duration
+                .set(Some(Duration::from_nanos(nanoseconds as u64)));
+        }
+        self.readback.resolve(encoder)
+    }
+
+    // to be called after submitting a resolved query
+    pub fn start_readback(&self) {
+        self.readback.start_readback();
+    }
+
+    pub fn take_frame_duration(&self) -> Option<Duration> {
+        self.frame_duration.take()
+    }
+}
@@ -1,76 +1,413 @@
 use crate::{
-    blit_graphics::BlitGraphics, parameters::Parameters, persistent_graphics::PersistentGraphics,
-    reloadable_graphics::ReloadableGraphics, render_texture_config::RenderTextureConfig,
+    blit_graphics::BlitGraphics,
+    bloom_graphics::{BloomGraphics, BloomPipelines},
+    gpu_timer::GpuTimer,
+    parameters::Parameters,
+    persistent_graphics::{Output, PersistentGraphics},
+    pipeline_statistics::PipelineStatistics,
+    reloadable_graphics::{ReloadableGraphics, ReloadableInputs},
+    render_error::RenderError,
+    render_texture_config::{RenderTextureConfig, UpscaleMode},
+    ssr_graphics::{SsrGraphics, SsrPipelines},
+    submit_profile::{SubmitPath, SubmitProfile},
+    upscale_graphics::{UpscaleGraphics, UpscalePipelines},
+    utils::{read_texture, save_png},
+};
+use anyhow::{Context, Result, anyhow, bail};
+use bytemuck::bytes_of;
+use std::{
+    cell::Cell,
+    ops::Range,
+    path::Path,
+    sync::{
+        Arc,
+        mpsc::{Receiver, TryRecvError},
+    },
+    time::Duration,
 };
-use anyhow::{Context, Ok, Result};
 use wgpu::{
-    BindGroup, Color, CommandEncoder, CommandEncoderDescriptor, LoadOp, Operations,
-    RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline, StoreOp, SurfaceTexture,
+    BindGroup, Color, CommandEncoder, CommandEncoderDescriptor, Extent3d, Limits, LoadOp,
+    Operations, PollType, PresentMode, RenderPass, RenderPassColorAttachment, RenderPassDescriptor,
+    RenderPassTimestampWrites, RenderPipeline, ShaderStages, StoreOp, Surface, SurfaceError,
+    SurfaceTexture, Texture, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
     TextureView, TextureViewDescriptor,
 };
-use winit::{dpi::PhysicalPosition, event_loop::ActiveEventLoop};
+use winit::{dpi::PhysicalPosition, event_loop::ActiveEventLoop, window::Window};
 
 #[derive(Debug)]
 pub struct Graphics {
     persistent: PersistentGraphics,
     reloadable: ReloadableGraphics,
+    pending_reload: Option<Receiver<Result<ReloadableGraphics>>>,
     blit: BlitGraphics,
+    bloom_pipelines: BloomPipelines,
+    bloom: BloomGraphics,
+    ssr_pipelines: SsrPipelines,
+    ssr: SsrGraphics,
+    upscale_pipelines: UpscalePipelines,
+    upscale: UpscaleGraphics,
     render_texture_config: RenderTextureConfig,
+    render_texture_format: TextureFormat,
     last_cursor_position: Option<PhysicalPosition<f64>>,
+    last_shader_error: Option<String>,
+    is_occluded: bool,
+    // only present with split submission, which is profiled against the single one at first
+    submit_profile: Option<SubmitProfile>,
+    // the latest GPU frame time, taken by the caller so every measurement is only seen once
+    gpu_frame_duration: Cell<Option<Duration>>,
 }
 
 impl Graphics {
     const CLEAR_COLOR: Color = Color::BLACK;
 
-    pub async fn init(event_loop: &ActiveEventLoop) -> Result<Self> {
-        let persistent = PersistentGraphics::init(event_loop).await?;
+    pub async fn init(
+        event_loop: &ActiveEventLoop,
+        preferred_adapter: Option<&str>,
+        limits: Limits,
+    ) -> Result<Self> {
+        let persistent = PersistentGraphics::init(event_loop, preferred_adapter, limits).await?;
+        Self::init_with_persistent(
+            persistent,
+            RenderTextureConfig::default(),
+            BlitGraphics::default_format(),
+        )
+    }
+
+    pub async fn init_off_screen(preferred_adapter: Option<&str>, limits: Limits) -> Result<Self> {
         let render_texture_config = RenderTextureConfig::default();
-        let reloadable = ReloadableGraphics::init(&persistent)?;
-        let blit = BlitGraphics::init(&persistent, &render_texture_config);
+        // the off-screen output is sized after the render texture, so only the device limits it
+        let max_size = limits.max_texture_dimension_2d;
+        let (width, height) = render_texture_config.render_texture_size(max_size, max_size);
+        let persistent =
+            PersistentGraphics::init_off_screen(width, height, preferred_adapter, limits).await?;
+        Self::init_with_persistent(
+            persistent,
+            render_texture_config,
+            BlitGraphics::default_format(),
+        )
+    }
+
+    pub async fn reinitialize(
+        self,
+        preferred_adapter: Option<&str>,
+        limits: Limits,
+    ) -> Result<Self> {
+        let Output::Window { window, .. } = &self.persistent.output else {
+            bail!("only window output can be reinitialized");
+        };
+        let window = window.clone();
+        let render_texture_config = self.render_texture_config.clone();
+        let render_texture_format = self.render_texture_format;
+        let vsync = self.persistent.is_vsync();
+        let split_submit = self.submit_profile.is_some();
+        // the old surface has to be gone before a new one can be created for the same window
+        drop(self);
+        let mut persistent =
+            PersistentGraphics::init_with_window(window, preferred_adapter, limits).await?;
+        // the new adapter might not be able to turn V-Sync off
+        if let Err(error) = persistent.set_vsync(vsync) {
+            eprintln!("{error:?}");
+        }
+        let mut graphics =
+            Self::init_with_persistent(persistent, render_texture_config, render_texture_format)?;
+        if split_submit {
+            graphics.enable_split_submit();
+        }
+        Ok(graphics)
+    }
+
+    fn init_with_persistent(
+        persistent: PersistentGraphics,
+        render_texture_config: RenderTextureConfig,
+        render_texture_format: TextureFormat,
+    ) -> Result<Self> {
+        let reloadable = ReloadableGraphics::init(&persistent, render_texture_format)?;
+        let blit = BlitGraphics::init(&persistent, &render_texture_config, render_texture_format);
+        let bloom_pipelines = BloomPipelines::init(&persistent, render_texture_format);
+        let bloom = BloomGraphics::init(&persistent, &blit);
+        let ssr_pipelines = SsrPipelines::init(&persistent, render_texture_format);
+        let ssr = SsrGraphics::init(&persistent, &blit);
+        let upscale_pipelines = UpscalePipelines::init(&persistent, render_texture_format);
+        let upscale = UpscaleGraphics::init(&persistent, &blit);
         Ok(Self {
             persistent,
             reloadable,
+            pending_reload: None,
             blit,
+            bloom_pipelines,
+            bloom,
+            ssr_pipelines,
+            ssr,
+            upscale_pipelines,
+            upscale,
             render_texture_config,
+            render_texture_format,
             last_cursor_position: None,
+            last_shader_error: None,
+            is_occluded: false,
+            submit_profile: None,
+            gpu_frame_duration: Cell::new(None),
         })
     }
 
     pub fn try_reload(&mut self) {
-        if let Err(error) = self.reload() {
-            println!("{error:?}");
+        // only debug builds read the shader from disk, so only they need to wait for compiling
+        if cfg!(debug_assertions) {
+            let inputs = ReloadableInputs::new(&self.persistent, self.render_texture_format);
+            self.pending_reload = Some(ReloadableGraphics::compile_in_background(inputs));
+        } else {
+            let result = self.reload();
+            self.finish_reload(result);
+        }
+    }
+
+    pub fn is_reloading(&self) -> bool {
+        self.pending_reload.is_some()
+    }
+
+    pub fn shader_short_hash(&self) -> String {
+        self.reloadable.compilation_meta().short_hash()
+    }
+
+    pub fn shader_age(&self) -> Duration {
+        self.reloadable.compilation_meta().compiled_at.elapsed()
+    }
+
+    pub fn has_shader_error(&self) -> bool {
+        self.last_shader_error.is_some()
+    }
+
+    // returns whether a reload finished, which changes the title status
+    pub fn poll_reload(&mut self) -> bool {
+        let Some(receiver) = &self.pending_reload else {
+            return false;
+        };
+        let result = match receiver.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => return false,
+            Err(TryRecvError::Disconnected) => {
+                Err(anyhow!("the shader compilation thread panicked"))
+            }
+        };
+        self.pending_reload = None;
+        let result = result
+            .map(|reloadable| self.reloadable = reloadable)
+            .context("failed to reload");
+        self.finish_reload(result);
+        true
+    }
+
+    fn finish_reload(&mut self, result: Result<()>) {
+        match result {
+            Ok(()) => self.last_shader_error = None,
+            Err(error) => {
+                println!("{error:?}");
+                let message = format!("{error:#}");
+                self.last_shader_error = message.lines().next().map(str::to_string);
+            }
         }
     }
 
     fn reload(&mut self) -> Result<()> {
-        self.reloadable = ReloadableGraphics::init(&self.persistent).context("failed to reload")?;
+        self.reloadable = ReloadableGraphics::init(&self.persistent, self.render_texture_format)
+            .context("failed to reload")?;
+        Ok(())
+    }
+
+    pub fn resize(&mut self, parameters: &mut Parameters) -> Result<()> {
+        // surfaces and render textures cannot have a zero size, so keep the old ones until restored
+        if self.is_minimized() {
+            return Ok(());
+        }
+        self.persistent.resize(parameters)?;
+        let (surface_width, surface_height) = self.persistent.surface_size();
+        if let Some((aspect_width, aspect_height)) = self.render_texture_config.aspect_override() {
+            parameters.update_aspect(aspect_width, aspect_height);
+        }
+        parameters.update_letterbox(surface_width, surface_height);
+        if Self::are_render_textures_stale(
+            &self.render_texture_config,
+            (surface_width, surface_height),
+            self.render_texture_size(),
+            self.upscale.size(),
+        ) {
+            self.recreate_render_textures();
+        }
         Ok(())
     }
 
-    pub fn resize(&self, parameters: &mut Parameters) -> Result<()> {
-        self.persistent.resize(parameters)
+    // the bind groups of the blit and bloom passes would still sample the old render texture,
+    // the upscaled texture has to follow the surface size
+    fn are_render_textures_stale(
+        render_texture_config: &RenderTextureConfig,
+        (surface_width, surface_height): (u32, u32),
+        render_texture_size: (u32, u32),
+        upscaled_size: (u32, u32),
+    ) -> bool {
+        let size = render_texture_config.render_texture_size(surface_width, surface_height);
+        size != render_texture_size || upscaled_size != (surface_width, surface_height)
     }
 
+    // takes effect with the next resize
+    pub fn set_aspect_override(&mut self, width: u32, height: u32) {
+        self.render_texture_config
+            .set_aspect_override(width, height);
+    }
+
+    pub fn render_texture_size(&self) -> (u32, u32) {
+        self.blit.render_texture_size()
+    }
+
+    // stalls until the GPU is idle, so only call this on user input
     pub fn update_render_texture_size(&mut self, delta: i32) {
         self.render_texture_config.update_render_texture_size(delta);
-        self.blit = BlitGraphics::init(&self.persistent, &self.render_texture_config);
+        self.recreate_render_textures();
+    }
+
+    pub fn wait_for_gpu(&self) -> Result<()> {
+        self.persistent
+            .device
+            .poll(PollType::wait_indefinitely())
+            .context("failed to wait for the GPU")?;
+        Ok(())
+    }
+
+    fn recreate_render_textures(&mut self) {
+        // passes submitted last frame may still sample the old render texture
+        if let Err(error) = self.wait_for_gpu() {
+            eprintln!("{error:?}");
+        }
+        self.blit = BlitGraphics::init(
+            &self.persistent,
+            &self.render_texture_config,
+            self.render_texture_format,
+        );
+        self.bloom = BloomGraphics::init(&self.persistent, &self.blit);
+        self.ssr = SsrGraphics::init(&self.persistent, &self.blit);
+        self.upscale = UpscaleGraphics::init(&self.persistent, &self.blit);
+    }
+
+    pub fn cycle_upscale_mode(&mut self) -> UpscaleMode {
+        self.render_texture_config.cycle_upscale_mode()
+    }
+
+    // the blit pass samples the render texture directly when there is nothing to filter
+    fn needs_upscale_pass(&self) -> bool {
+        let (width, height) = self.render_texture_size();
+        let (surface_width, surface_height) = self.persistent.surface_size();
+        let is_upscaled = width < surface_width || height < surface_height;
+        is_upscaled && self.render_texture_config.upscale_mode() != UpscaleMode::Bilinear
+    }
+
+    // unlike Rgba32Float, this can be sampled with filtering without extra features
+    const HDR_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+    // bright areas keep their full brightness for bloom instead of being clipped at 1
+    pub fn toggle_hdr(&mut self) -> Result<()> {
+        let format = if self.render_texture_format == Self::HDR_FORMAT {
+            BlitGraphics::default_format()
+        } else {
+            Self::HDR_FORMAT
+        };
+        self.reloadable = ReloadableGraphics::init(&self.persistent, format)
+            .context("failed to recreate the render pipeline")?;
+        // a pending reload would bring back the old format
+        self.pending_reload = None;
+        self.bloom_pipelines = BloomPipelines::init(&self.persistent, format);
+        self.ssr_pipelines = SsrPipelines::init(&self.persistent, format);
+        self.upscale_pipelines = UpscalePipelines::init(&self.persistent, format);
+        self.render_texture_format = format;
+        self.recreate_render_textures();
+        Ok(())
+    }
+
+    pub fn set_vsync(&mut self, enabled: bool) -> Result<()> {
+        self.persistent.set_vsync(enabled)
+    }
+
+    pub fn toggle_vsync(&mut self) -> Result<()> {
+        self.set_vsync(!self.persistent.is_vsync())
+    }
+
+    pub fn adapter_info_string(&self) -> String {
+        self.persistent.adapter_info_string()
+    }
+
+    pub fn present_mode(&self) -> PresentMode {
+        self.persistent.present_mode()
+    }
+
+    pub fn is_vsync(&self) -> bool {
+        self.persistent.is_vsync()
+    }
+
+    pub fn is_hdr(&self) -> bool {
+        self.render_texture_format == Self::HDR_FORMAT
+    }
+
+    pub fn shared_window(&self) -> Option<Arc<Window>> {
+        self.persistent.shared_window()
+    }
+
+    pub fn request_redraw(&self) {
+        if let Some(window) = self.persistent.window() {
+            window.request_redraw();
+        }
+    }
+
+    pub fn enable_split_submit(&mut self) {
+        let profile = SubmitProfile::default();
+        if self.persistent.gpu_timer.is_none() {
+            eprintln!(
+                "the adapter does not support timestamp queries, split submission is not profiled"
+            );
+            profile.stop();
+        }
+        self.submit_profile = Some(profile);
+    }
+
+    pub fn set_occluded(&mut self, occluded: bool) {
+        let was_occluded = self.is_occluded;
+        self.is_occluded = occluded;
+        if was_occluded && !occluded {
+            self.request_redraw();
+        }
+    }
+
+    pub fn halve_render_texture_size(&mut self) -> bool {
+        let changed = self.render_texture_config.halve_render_texture_size();
+        if changed {
+            self.update_render_texture_size(0);
+        }
+        changed
     }
 
     pub fn update_parameters_buffer(&self, parameters: &Parameters) {
         self.persistent.update_parameters_buffer(parameters)
     }
 
+    pub fn fragment_shader_invocations(&self) -> Option<u64> {
+        self.persistent
+            .pipeline_statistics
+            .as_ref()
+            .and_then(PipelineStatistics::fragment_shader_invocations)
+    }
+
+    pub fn is_cursor_grabbed(&self) -> bool {
+        self.persistent.is_cursor_grabbed
+    }
+
     pub fn move_cursor(
         &mut self,
         position: PhysicalPosition<f64>,
     ) -> Result<Option<PhysicalPosition<f64>>> {
         if self.persistent.is_cursor_grabbed
+            && let Some(window) = self.persistent.window()
             && let Some(last_position) = self.last_cursor_position
         {
             let x = position.x - last_position.x;
             let y = position.y - last_position.y;
-            self.persistent
-                .window
+            window
                 .set_cursor_position(last_position)
                 .context("failed to lock cursor in place")?;
             Ok(Some(PhysicalPosition { x, y }))
@@ -80,6 +417,17 @@ impl Graphics {
         }
     }
 
+    pub fn set_title_status(&self, status: &str) {
+        if let Some(window) = self.persistent.window() {
+            let title = PersistentGraphics::TITLE;
+            let error = match &self.last_shader_error {
+                Some(error) => format!(" [SHADER ERROR: {error}]"),
+                None => String::new(),
+            };
+            window.set_title(&format!("{title}{status}{error}"));
+        }
+    }
+
     pub fn grab_cursor(&mut self) {
         self.persistent.grab_cursor()
     }
@@ -88,78 +436,536 @@ impl Graphics {
         self.persistent.ungrab_cursor()
     }
 
-    pub fn render(&self) -> Result<()> {
+    pub fn hide_cursor(&self) {
+        if let Some(window) = self.persistent.window() {
+            window.set_cursor_visible(false);
+        }
+    }
+
+    // minimized windows have a zero inner size
+    fn is_minimized(&self) -> bool {
+        let (width, height) = self.persistent.surface_size();
+        width == 0 || height == 0
+    }
+
+    pub fn render(&self, parameters: &Parameters) -> Result<(), RenderError> {
+        if self.is_minimized() {
+            // keeps the updates going, so time does not freeze while minimized
+            if !self.is_occluded {
+                self.request_redraw();
+            }
+            return Ok(());
+        }
+        match self.submit_profile.as_ref().map(SubmitProfile::next_path) {
+            Some(SubmitPath::Split) => self.render_split_submit(parameters),
+            Some(SubmitPath::Single) | None => {
+                self.render_with_submit_path(parameters, SubmitPath::Single)
+            }
+        }
+    }
+
+    pub fn render_split_submit(&self, parameters: &Parameters) -> Result<(), RenderError> {
+        self.render_with_submit_path(parameters, SubmitPath::Split)
+    }
+
+    fn render_with_submit_path(
+        &self,
+        parameters: &Parameters,
+        path: SubmitPath,
+    ) -> Result<(), RenderError> {
         let PersistentGraphics {
             device,
-            surface,
+            output,
             queue,
-            window,
             ..
         } = &self.persistent;
+        let gpu_timer = self.persistent.gpu_timer.as_ref();
         let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor::default());
-        self.do_render_texture_pass(&mut encoder);
-        let frame = surface
-            .get_current_texture()
-            .context("failed to get frame texture")?;
-        self.do_blit_pass(&mut encoder, &frame);
-        queue.submit(Some(encoder.finish()));
+        let resolved_statistics = self.encode_render_texture_passes(&mut encoder, parameters);
+        if path == SubmitPath::Split {
+            // the GPU can start ray marching while the surface texture is still being waited for
+            queue.submit(Some(encoder.finish()));
+            encoder = device.create_command_encoder(&CommandEncoderDescriptor::default());
+        }
+        let resolved_timer = match output {
+            Output::Window {
+                window, surface, ..
+            } => {
+                let frame = self.get_current_frame(surface)?;
+                let resolved_timer = self.encode_final_blit_pass(&mut encoder, &frame.texture);
+                queue.submit(Some(encoder.finish()));
+                self.present(window, frame);
+                resolved_timer
+            }
+            Output::OffScreen { texture } => {
+                let resolved_timer = self.encode_final_blit_pass(&mut encoder, texture);
+                queue.submit(Some(encoder.finish()));
+                resolved_timer
+            }
+        };
+        self.start_readbacks(resolved_statistics, gpu_timer, resolved_timer, path);
+        Ok(())
+    }
+
+    // returns whether the pipeline statistics got resolved
+    fn encode_render_texture_passes(
+        &self,
+        encoder: &mut CommandEncoder,
+        parameters: &Parameters,
+    ) -> bool {
+        let pipeline_statistics = self.persistent.pipeline_statistics.as_ref();
+        self.do_render_texture_pass(
+            encoder,
+            parameters,
+            &self.blit.render_texture,
+            &self.ssr.normal_depth_texture,
+            pipeline_statistics,
+            self.persistent.gpu_timer.as_ref(),
+        );
+        let resolved_statistics =
+            pipeline_statistics.is_some_and(|statistics| statistics.resolve(encoder));
+        if parameters.is_bloom_enabled() {
+            self.do_bloom_passes(encoder);
+        }
+        if parameters.is_ssr_enabled() {
+            self.do_ssr_pass(encoder);
+        }
+        if self.needs_upscale_pass() {
+            self.do_upscale_pass(encoder);
+        }
+        resolved_statistics
+    }
+
+    // returns whether the GPU timestamps got resolved
+    fn encode_final_blit_pass(&self, encoder: &mut CommandEncoder, target: &Texture) -> bool {
+        let gpu_timer = self.persistent.gpu_timer.as_ref();
+        self.do_blit_pass(encoder, target, gpu_timer.map(GpuTimer::end_of_frame));
+        gpu_timer.is_some_and(|timer| timer.resolve(encoder))
+    }
+
+    fn get_current_frame(&self, surface: &Surface) -> Result<SurfaceTexture, RenderError> {
+        match surface.get_current_texture() {
+            // the surface just needs to be reconfigured, e.g. after a resize
+            Err(SurfaceError::Outdated) => {
+                self.persistent
+                    .configure_surface()
+                    .context("failed to reconfigure the outdated surface")?;
+                surface.get_current_texture()
+            }
+            frame => frame,
+        }
+        .map_err(RenderError::Surface)
+    }
+
+    fn present(&self, window: &Window, frame: SurfaceTexture) {
         window.pre_present_notify();
         frame.present();
-        window.request_redraw();
-        Ok(())
+        // nothing would be seen, set_occluded restarts the redraws
+        if !self.is_occluded {
+            window.request_redraw();
+        }
     }
 
-    fn do_render_texture_pass(&self, encoder: &mut CommandEncoder) {
-        let render_texture_view = self
-            .blit
-            .render_texture
-            .create_view(&TextureViewDescriptor::default());
-        Self::do_render_pass(
+    // to be called after all passes of the frame are submitted
+    fn start_readbacks(
+        &self,
+        resolved_statistics: bool,
+        gpu_timer: Option<&GpuTimer>,
+        resolved_timer: bool,
+        path: SubmitPath,
+    ) {
+        if let Some(statistics) = &self.persistent.pipeline_statistics
+            && resolved_statistics
+        {
+            statistics.start_readback();
+        }
+        let Some(timer) = gpu_timer else {
+            return;
+        };
+        // resolving finished the readback of an earlier frame
+        let frame_duration = timer.take_frame_duration();
+        if frame_duration.is_some() {
+            self.gpu_frame_duration.set(frame_duration);
+        }
+        let profile = self
+            .submit_profile
+            .as_ref()
+            .filter(|profile| profile.is_profiling());
+        if let Some(profile) = profile {
+            profile.record(frame_duration);
+        }
+        if resolved_timer {
+            timer.start_readback();
+            if let Some(profile) = profile {
+                profile.set_pending_path(path);
+            }
+        }
+    }
+
+    // only measured when the adapter supports timestamp queries
+    pub fn take_gpu_frame_duration(&self) -> Option<Duration> {
+        self.gpu_frame_duration.take()
+    }
+
+    pub fn save_output(&self, path: &Path) -> Result<()> {
+        let (width, height, rgba) = self.read_output()?;
+        save_png(path, width, height, &rgba)
+    }
+
+    pub fn read_output(&self) -> Result<(u32, u32, Vec<u8>)> {
+        let PersistentGraphics {
+            device,
+            output,
+            queue,
+            ..
+        } = &self.persistent;
+        let Output::OffScreen { texture } = output else {
+            bail!("only off-screen output can be read back");
+        };
+        let rgba = read_texture(device, queue, texture)?;
+        Ok((texture.width(), texture.height(), rgba))
+    }
+
+    // stalls until the frame is read back, bloom and the blit effects are left out
+    pub fn save_render_at_resolution(
+        &self,
+        parameters: &Parameters,
+        width: u32,
+        height: u32,
+        path: &Path,
+    ) -> Result<()> {
+        let PersistentGraphics { device, queue, .. } = &self.persistent;
+        if self.render_texture_format != BlitGraphics::default_format() {
+            bail!("saving renders is only supported for the default render texture format");
+        }
+        let max_size = device.limits().max_texture_dimension_2d;
+        if width == 0 || height == 0 || width > max_size || height > max_size {
+            bail!("render size {width}x{height} is not between 1x1 and {max_size}x{max_size}");
+        }
+        let texture = device.create_texture(&TextureDescriptor {
+            label: Some("high_resolution_render_texture"),
+            dimension: TextureDimension::D2,
+            size: Extent3d {
+                width,
+                height,
+                ..Default::default()
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            format: self.render_texture_format,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let mut high_resolution_parameters = *parameters;
+        high_resolution_parameters.update_aspect(width, height);
+        self.update_parameters_buffer(&high_resolution_parameters);
+        let normal_depth_texture = SsrGraphics::create_normal_depth_texture(device, width, height);
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor::default());
+        self.do_render_texture_pass(
+            &mut encoder,
+            &high_resolution_parameters,
+            &texture,
+            &normal_depth_texture,
+            None,
+            None,
+        );
+        queue.submit(Some(encoder.finish()));
+        // buffer writes are ordered with submissions, so the next frame sees the original again
+        self.update_parameters_buffer(parameters);
+        let rgba = read_texture(device, queue, &texture)?;
+        save_png(path, width, height, &rgba)
+    }
+
+    fn do_render_texture_pass(
+        &self,
+        encoder: &mut CommandEncoder,
+        parameters: &Parameters,
+        render_texture: &Texture,
+        normal_depth_texture: &Texture,
+        pipeline_statistics: Option<&PipelineStatistics>,
+        gpu_timer: Option<&GpuTimer>,
+    ) {
+        let render_texture_view = render_texture.create_view(&TextureViewDescriptor::default());
+        let normal_depth_texture_view =
+            normal_depth_texture.create_view(&TextureViewDescriptor::default());
+        let mut render_pass = Self::begin_render_pass(
             encoder,
             "render_pass",
-            &render_texture_view,
+            &[&render_texture_view, &normal_depth_texture_view],
             &self.reloadable.render_pipeline,
-            &self.persistent.parameters_bind_group,
+            &[&self.persistent.parameters_bind_group],
+            gpu_timer.map(GpuTimer::start_of_frame),
+        );
+        if self.persistent.supports_push_constants() {
+            let fast_parameters = parameters.fast_parameters();
+            render_pass.set_push_constants(ShaderStages::FRAGMENT, 0, bytes_of(&fast_parameters));
+        }
+        if let Some(statistics) = pipeline_statistics {
+            statistics.begin(&mut render_pass);
+        }
+        // viewports are laid out side by side, the instance index tells the shader which one it is
+        let viewport_count = parameters.viewport_count();
+        let viewport_width = render_texture.width() as f32 / viewport_count as f32;
+        let viewport_height = render_texture.height() as f32;
+        for viewport_index in 0..viewport_count {
+            let x = viewport_index as f32 * viewport_width;
+            render_pass.set_viewport(x, 0.0, viewport_width, viewport_height, 0.0, 1.0);
+            render_pass.draw(Self::QUAD_VERTICES, viewport_index..viewport_index + 1);
+        }
+        if let Some(statistics) = pipeline_statistics {
+            statistics.end(&mut render_pass);
+        }
+    }
+
+    fn do_bloom_passes(&self, encoder: &mut CommandEncoder) {
+        let BloomPipelines {
+            threshold_render_pipeline,
+            blur_horizontal_render_pipeline,
+            blur_vertical_render_pipeline,
+        } = &self.bloom_pipelines;
+        let BloomGraphics {
+            bright_texture_view,
+            blurred_texture_view,
+            threshold_bind_group,
+            blur_horizontal_bind_group,
+            blur_vertical_bind_group,
+            ..
+        } = &self.bloom;
+        let parameters_bind_group = &self.persistent.parameters_bind_group;
+        Self::do_render_pass(
+            encoder,
+            "bloom_threshold_render_pass",
+            bright_texture_view,
+            threshold_render_pipeline,
+            &[threshold_bind_group, parameters_bind_group],
+        );
+        Self::do_render_pass(
+            encoder,
+            "bloom_blur_horizontal_render_pass",
+            blurred_texture_view,
+            blur_horizontal_render_pipeline,
+            &[blur_horizontal_bind_group, parameters_bind_group],
+        );
+        Self::do_render_pass(
+            encoder,
+            "bloom_blur_vertical_render_pass",
+            bright_texture_view,
+            blur_vertical_render_pipeline,
+            &[blur_vertical_bind_group, parameters_bind_group],
         );
     }
 
-    fn do_blit_pass(&self, encoder: &mut CommandEncoder, frame: &SurfaceTexture) {
-        let frame_texture_view = frame.texture.create_view(&TextureViewDescriptor::default());
+    fn do_ssr_pass(&self, encoder: &mut CommandEncoder) {
         Self::do_render_pass(
+            encoder,
+            "ssr_render_pass",
+            &self.ssr.reflection_texture_view,
+            &self.ssr_pipelines.render_pipeline,
+            &[
+                &self.blit.blit_bind_group,
+                &self.persistent.parameters_bind_group,
+                &self.ssr.normal_depth_bind_group,
+            ],
+        );
+    }
+
+    fn do_upscale_pass(&self, encoder: &mut CommandEncoder) {
+        let UpscalePipelines {
+            nearest_render_pipeline,
+            lanczos_render_pipeline,
+        } = &self.upscale_pipelines;
+        let render_pipeline = match self.render_texture_config.upscale_mode() {
+            UpscaleMode::Bilinear => unreachable!("bilinear upscaling is done by the sampler"),
+            UpscaleMode::Nearest => nearest_render_pipeline,
+            UpscaleMode::Lanczos => lanczos_render_pipeline,
+        };
+        Self::do_render_pass(
+            encoder,
+            "upscale_render_pass",
+            &self.upscale.upscaled_texture_view,
+            render_pipeline,
+            &[&self.blit.blit_bind_group],
+        );
+    }
+
+    const ERROR_BAR_HEIGHT_FRACTION: f32 = 0.02;
+
+    fn do_blit_pass(
+        &self,
+        encoder: &mut CommandEncoder,
+        target: &Texture,
+        timestamp_writes: Option<RenderPassTimestampWrites>,
+    ) {
+        let target_view = target.create_view(&TextureViewDescriptor::default());
+        let image_bind_group = if self.needs_upscale_pass() {
+            &self.upscale.upscaled_bind_group
+        } else {
+            &self.blit.blit_bind_group
+        };
+        let mut render_pass = Self::begin_render_pass(
             encoder,
             "blit_render_pass",
-            &frame_texture_view,
+            &[&target_view],
             &self.persistent.blit_render_pipeline,
-            &self.blit.blit_bind_group,
+            &[
+                image_bind_group,
+                &self.persistent.parameters_bind_group,
+                &self.bloom.composite_bind_group,
+                &self.ssr.composite_bind_group,
+            ],
+            timestamp_writes,
         );
+        let single_instance = 0..1;
+        render_pass.draw(Self::QUAD_VERTICES, single_instance.clone());
+        if self.last_shader_error.is_some() {
+            // a bar at the top of the screen, the still running old shader is shown below it
+            let width = target.width() as f32;
+            let height = target.height() as f32 * Self::ERROR_BAR_HEIGHT_FRACTION;
+            render_pass.set_pipeline(&self.persistent.error_render_pipeline);
+            render_pass.set_viewport(0.0, 0.0, width, height, 0.0, 1.0);
+            render_pass.draw(Self::QUAD_VERTICES, single_instance);
+        }
     }
 
+    const QUAD_VERTICES: Range<u32> = 0..4;
+
     fn do_render_pass(
         encoder: &mut CommandEncoder,
         label: &'static str,
         view: &TextureView,
         render_pipeline: &RenderPipeline,
-        bind_group: &BindGroup,
+        bind_groups: &[&BindGroup],
     ) {
+        let mut render_pass =
+            Self::begin_render_pass(encoder, label, &[view], render_pipeline, bind_groups, None);
+        let single_instance = 0..1;
+        render_pass.draw(Self::QUAD_VERTICES, single_instance);
+    }
+
+    fn begin_render_pass<'encoder>(
+        encoder: &'encoder mut CommandEncoder,
+        label: &'static str,
+        views: &[&TextureView],
+        render_pipeline: &RenderPipeline,
+        bind_groups: &[&BindGroup],
+        timestamp_writes: Option<RenderPassTimestampWrites>,
+    ) -> RenderPass<'encoder> {
+        let color_attachments: Vec<_> = views
+            .iter()
+            .map(|view| {
+                Some(RenderPassColorAttachment {
+                    view,
+                    depth_slice: None,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(Self::CLEAR_COLOR),
+                        store: StoreOp::Store,
+                    },
+                })
+            })
+            .collect();
         let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
             label: Some(label),
-            color_attachments: &[Some(RenderPassColorAttachment {
-                view,
-                depth_slice: None,
-                resolve_target: None,
-                ops: Operations {
-                    load: LoadOp::Clear(Self::CLEAR_COLOR),
-                    store: StoreOp::Store,
-                },
-            })],
+            color_attachments: &color_attachments,
             depth_stencil_attachment: None,
-            timestamp_writes: None,
+            timestamp_writes,
             occlusion_query_set: None,
         });
         render_pass.set_pipeline(render_pipeline);
-        render_pass.set_bind_group(0, bind_group, &[]);
-        let vertices = 0..4; // a quad
-        let single_instance = 0..1;
-        render_pass.draw(vertices, single_instance);
+        for (index, bind_group) in (0..).zip(bind_groups) {
+            render_pass.set_bind_group(index, *bind_group, &[]);
+        }
+        render_pass
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::Camera;
+    use pollster::block_on;
+    use wgpu::{Instance, RequestAdapterOptions};
+
+    #[test]
+    fn shrinking_surface_makes_render_textures_stale() {
+        // the default config renders 1920x1080, which both surfaces cap
+        let config = RenderTextureConfig::default();
+        assert_eq!(config.render_texture_size(400, 300), (400, 300));
+        assert!(Graphics::are_render_textures_stale(
+            &config,
+            (400, 300),
+            (800, 600),
+            (800, 600),
+        ));
+    }
+
+    #[test]
+    fn growing_surface_makes_render_textures_stale() {
+        let config = RenderTextureConfig::default();
+        assert_eq!(config.render_texture_size(1024, 768), (1024, 768));
+        assert!(Graphics::are_render_textures_stale(
+            &config,
+            (1024, 768),
+            (800, 600),
+            (800, 600),
+        ));
+    }
+
+    #[test]
+    fn uncapped_render_texture_still_follows_the_upscaled_size() {
+        // the render texture stays 160x90 on both surfaces, but the upscaled one does not
+        let mut config = RenderTextureConfig::default();
+        config.update_render_texture_size(-11);
+        assert_eq!(config.render_texture_size(800, 600), (160, 90));
+        assert_eq!(config.render_texture_size(1024, 768), (160, 90));
+        assert!(Graphics::are_render_textures_stale(
+            &config,
+            (1024, 768),
+            (160, 90),
+            (800, 600),
+        ));
+    }
+
+    #[test]
+    fn unchanged_surface_keeps_render_textures() {
+        let config = RenderTextureConfig::default();
+        assert!(!Graphics::are_render_textures_stale(
+            &config,
+            (800, 600),
+            (800, 600),
+            (800, 600),
+        ));
+    }
+
+    #[test]
+    fn renders_a_frame_off_screen() {
+        // machines without any adapter cannot render, so there is nothing to test
+        if block_on(Instance::default().request_adapter(&RequestAdapterOptions::default())).is_err()
+        {
+            return;
+        }
+        let persistent = block_on(PersistentGraphics::init_off_screen(
+            16,
+            16,
+            None,
+            Limits::default(),
+        ))
+        .unwrap();
+        let mut graphics = Graphics::init_with_persistent(
+            persistent,
+            RenderTextureConfig::default(),
+            BlitGraphics::default_format(),
+        )
+        .unwrap();
+        let mut parameters = Parameters::default();
+        graphics.resize(&mut parameters).unwrap();
+        parameters.update_camera(&Camera::default());
+        graphics.update_parameters_buffer(&parameters);
+        graphics.render(&parameters).unwrap();
+        let (width, height, rgba) = graphics.read_output().unwrap();
+        assert_eq!((width, height), (16, 16));
+        assert_eq!(rgba.len(), 16 * 16 * 4);
+        let is_black = |pixel: &[u8]| pixel[..3].iter().all(|&channel| channel == 0);
+        assert!(!rgba.chunks_exact(4).all(is_black), "the frame is black");
     }
 }
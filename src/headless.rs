@@ -0,0 +1,17 @@
+use crate::{arguments::Arguments, initialized_app::InitializedApp, parameters::Parameters};
+use anyhow::{Context, Result};
+use pollster::block_on;
+use std::path::PathBuf;
+
+pub fn run(arguments: &Arguments) -> Result<()> {
+    let mut app = block_on(InitializedApp::init_off_screen(arguments))
+        .context("failed to initialize headless app")?;
+    for scene_index in 0..Parameters::NUM_SCENES as usize {
+        app.set_scene_index(scene_index);
+        app.draw().context("failed to draw")?;
+        let path = PathBuf::from(format!("scene_{scene_index:02}.png"));
+        app.save_output(&path)?;
+        eprintln!("saved {}", path.display());
+    }
+    Ok(())
+}
@@ -1,7 +1,8 @@
 use bitflags::bitflags;
+use std::time::{Duration, Instant};
 
 bitflags! {
-    #[derive(Debug, Default, Clone, Copy)]
+    #[derive(Debug, Default, Clone, Copy, PartialEq)]
     pub struct HeldKeys: u16 {
         const MoveForward = 1 << 0;
         const MoveBackward = 1 << 1;
@@ -15,12 +16,22 @@ bitflags! {
         const YawLeft = 1 << 9;
         const Shift = 1 << 10;
         const Control = 1 << 11;
+        const Alt = 1 << 12;
+        const RollClockwise = 1 << 13;
+        const RollCounterClockwise = 1 << 14;
     }
 }
 
 type Magnitude = i8;
 
 impl HeldKeys {
+    const BITS: usize = u16::BITS as usize;
+
+    // only meaningful for single keys
+    fn index(self) -> usize {
+        self.bits().trailing_zeros() as usize
+    }
+
     pub fn is_shift_pressed(&self) -> bool {
         self.contains(Self::Shift)
     }
@@ -29,6 +40,10 @@ impl HeldKeys {
         self.contains(Self::Control)
     }
 
+    pub fn is_alt_pressed(&self) -> bool {
+        self.contains(Self::Alt)
+    }
+
     fn magnitude(&self, positive: Self, negative: Self) -> Magnitude {
         Magnitude::from(self.contains(positive)) - Magnitude::from(self.contains(negative))
     }
@@ -52,4 +67,106 @@ impl HeldKeys {
     pub fn yaw_magnitude(&self) -> Magnitude {
         self.magnitude(Self::YawRight, Self::YawLeft)
     }
+
+    pub fn roll_magnitude(&self) -> Magnitude {
+        self.magnitude(Self::RollClockwise, Self::RollCounterClockwise)
+    }
+}
+
+// when each held key was pressed, to tell short taps apart from holding it
+#[derive(Debug, Clone)]
+pub struct HeldKeyTimes {
+    tap_threshold: Duration,
+    last_set_time: [Option<Instant>; HeldKeys::BITS],
+}
+
+impl HeldKeyTimes {
+    const DEFAULT_TAP_THRESHOLD: Duration = Duration::from_millis(200);
+
+    pub fn set(&mut self, key: HeldKeys, pressed: bool, now: Instant) {
+        let time = &mut self.last_set_time[key.index()];
+        if pressed {
+            // key repeats keep the time of the first press
+            time.get_or_insert(now);
+        } else {
+            *time = None;
+        }
+    }
+
+    // to be checked on release, before set forgets the press
+    pub fn is_tap(&self, key: HeldKeys, now: Instant) -> bool {
+        self.last_set_time[key.index()].is_some_and(|time| now - time < self.tap_threshold)
+    }
+
+    // other input while a key is held makes it a combination instead of a tap
+    pub fn interrupt(&mut self) {
+        self.last_set_time = [None; HeldKeys::BITS];
+    }
+}
+
+impl Default for HeldKeyTimes {
+    fn default() -> Self {
+        Self {
+            tap_threshold: Self::DEFAULT_TAP_THRESHOLD,
+            last_set_time: [None; HeldKeys::BITS],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_roll_without_roll_keys() {
+        assert_eq!(HeldKeys::empty().roll_magnitude(), 0);
+        assert_eq!(
+            HeldKeys::all()
+                .difference(HeldKeys::RollClockwise | HeldKeys::RollCounterClockwise)
+                .roll_magnitude(),
+            0
+        );
+    }
+
+    #[test]
+    fn clockwise_roll_is_positive() {
+        assert_eq!(HeldKeys::RollClockwise.roll_magnitude(), 1);
+    }
+
+    #[test]
+    fn counter_clockwise_roll_is_negative() {
+        assert_eq!(HeldKeys::RollCounterClockwise.roll_magnitude(), -1);
+    }
+
+    #[test]
+    fn opposite_roll_keys_cancel_out() {
+        let keys = HeldKeys::RollClockwise | HeldKeys::RollCounterClockwise;
+        assert_eq!(keys.roll_magnitude(), 0);
+    }
+
+    #[test]
+    fn roll_keys_do_not_affect_other_rotations() {
+        let keys = HeldKeys::RollClockwise | HeldKeys::RollCounterClockwise;
+        assert_eq!(keys.pitch_magnitude(), 0);
+        assert_eq!(keys.yaw_magnitude(), 0);
+    }
+
+    #[test]
+    fn roll_combines_with_pitch_and_yaw() {
+        let keys = HeldKeys::RollCounterClockwise | HeldKeys::PitchUp | HeldKeys::YawRight;
+        assert_eq!(keys.roll_magnitude(), -1);
+        assert_eq!(keys.pitch_magnitude(), -1);
+        assert_eq!(keys.yaw_magnitude(), 1);
+    }
+
+    #[test]
+    fn roll_key_taps_are_timed_separately() {
+        let mut times = HeldKeyTimes::default();
+        let start = Instant::now();
+        times.set(HeldKeys::RollClockwise, true, start);
+        let later = start + HeldKeyTimes::DEFAULT_TAP_THRESHOLD;
+        times.set(HeldKeys::RollCounterClockwise, true, later);
+        assert!(!times.is_tap(HeldKeys::RollClockwise, later));
+        assert!(times.is_tap(HeldKeys::RollCounterClockwise, later));
+    }
 }
@@ -1,50 +1,362 @@
 use crate::{
-    camera::Camera, graphics::Graphics, held_keys::HeldKeys, parameters::Parameters, timing::Timing,
+    arguments::Arguments,
+    benchmark::BenchmarkResult,
+    camera::{Camera, CameraSnapshot, CameraTransition},
+    demo_mode::DemoMode,
+    graphics::Graphics,
+    held_keys::{HeldKeyTimes, HeldKeys},
+    input_sequence::{InputEventKind, InputRecorder, InputReplay, InputSequence, KeyInput},
+    kiosk::KioskController,
+    midi::MidiInput,
+    parameters::Parameters,
+    persistent_graphics::PersistentGraphics,
+    presets::FractalPreset,
+    reloadable_graphics::ReloadableGraphics,
+    render_error::RenderError,
+    timing::{StepMode, TimeMode, Timing},
+    utils::{halton, percentile},
 };
-use anyhow::{Context, Ok, Result};
+use anyhow::{Context, Ok, Result, bail};
+use cgmath::{Rad, Zero};
+use std::{
+    fs::{self, write},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use wgpu::Limits;
 use winit::{
     dpi::PhysicalPosition,
     event::{ElementState, KeyEvent, MouseButton, MouseScrollDelta},
     event_loop::ActiveEventLoop,
-    keyboard::NamedKey,
+    keyboard::{KeyCode, NamedKey, PhysicalKey},
+    window::{Theme, Window},
 };
 
 #[derive(Debug)]
 pub struct InitializedApp {
     graphics: Graphics,
     held_keys: HeldKeys,
+    held_key_times: HeldKeyTimes,
     parameters: Parameters,
     camera: Camera,
+    camera_poses: Vec<Camera>,
+    camera_transition: Option<CameraTransition>,
     timing: Timing,
+    midi: Option<MidiInput>,
+    demo_mode: Option<DemoMode>,
+    kiosk: Option<KioskController>,
+    exit_requested: bool,
+    jitter_index: u32,
+    input_recorder: Option<InputRecorder>,
+    input_replay: Option<InputReplay>,
+    last_input_sequence: Option<InputSequence>,
+    // the last applied one, so the next press continues from it
+    preset: Option<FractalPreset>,
+    pending_drop: Option<(PathBuf, Instant)>,
+    shader_backup: Option<String>,
+    strict: bool,
+    last_validation: Instant,
+    prev_parameters: Option<Parameters>,
+    last_parameters_diff: Instant,
 }
 
 impl InitializedApp {
-    pub async fn init(event_loop: &ActiveEventLoop) -> Result<Self> {
-        let graphics = Graphics::init(event_loop).await?;
+    const PARAMETERS_SNAPSHOT_PATH: &str = "parameters_snapshot.wgsl";
+
+    fn limits(arguments: &Arguments) -> Limits {
+        if arguments.relaxed_limits {
+            PersistentGraphics::relaxed_limits()
+        } else {
+            Limits::default()
+        }
+    }
+
+    pub async fn init(event_loop: &ActiveEventLoop, arguments: &Arguments) -> Result<Self> {
+        let graphics = Graphics::init(
+            event_loop,
+            arguments.gpu.as_deref(),
+            Self::limits(arguments),
+        )
+        .await?;
+        let mut app = Self::init_with_graphics(graphics, arguments)?;
+        if arguments.no_vsync
+            && let Err(error) = app.graphics.set_vsync(false)
+        {
+            eprintln!("{error:?}");
+        }
+        if arguments.split_submit {
+            app.graphics.enable_split_submit();
+        }
+        if arguments.kiosk {
+            app.graphics.hide_cursor();
+            app.kiosk = Some(KioskController::start());
+        }
+        Ok(app)
+    }
+
+    pub async fn init_off_screen(arguments: &Arguments) -> Result<Self> {
+        let graphics =
+            Graphics::init_off_screen(arguments.gpu.as_deref(), Self::limits(arguments)).await?;
+        let mut app = Self::init_with_graphics(graphics, arguments)?;
+        // saved frames should not depend on how long rendering took
+        app.timing
+            .set_step_mode(StepMode::FixedStep(Timing::DEFAULT_FIXED_STEP_FPS));
+        Ok(app)
+    }
+
+    fn init_with_graphics(mut graphics: Graphics, arguments: &Arguments) -> Result<Self> {
         let mut parameters = Parameters::default();
+        if let Some((width, height)) = arguments.aspect {
+            graphics.set_aspect_override(width, height);
+        }
         graphics
             .resize(&mut parameters)
             .context("failed to resize the surface")?;
-        Ok(Self {
+        let mut timing = Timing::init();
+        if let Some(path) = &arguments.log_fps {
+            timing.set_log_file(path)?;
+        }
+        if let Some(ms) = arguments.frame_budget_ms {
+            timing.set_frame_budget_ms(ms);
+        }
+        if let Some((min, max)) = arguments.time_factor_limits {
+            timing.set_time_factor_limits(min, max);
+        }
+        let midi = if arguments.midi {
+            MidiInput::connect(Path::new(MidiInput::MAPPING_PATH))
+                .context("failed to set up MIDI input")?
+        } else {
+            None
+        };
+        let mut camera = Camera::default();
+        if let Some(speed) = arguments.orbit_speed {
+            camera.set_orbit_angle_per_second(Rad(speed));
+        }
+        if let Some(pixels_per_turn) = arguments.mouse_sensitivity {
+            camera.set_rotation_per_pixel(Camera::from_sensitivity_dpi(pixels_per_turn));
+        }
+        if let Some(alpha) = arguments.smooth_alpha {
+            camera.set_smooth_alpha(alpha);
+        }
+        let camera_poses = Self::load_camera_bookmark(&camera).into_iter().collect();
+        let app = Self {
             graphics,
             held_keys: HeldKeys::default(),
+            held_key_times: HeldKeyTimes::default(),
             parameters,
-            camera: Camera::default(),
-            timing: Timing::init(),
-        })
+            camera,
+            camera_poses,
+            camera_transition: None,
+            timing,
+            midi,
+            demo_mode: None,
+            kiosk: None,
+            exit_requested: false,
+            jitter_index: 0,
+            input_recorder: None,
+            input_replay: None,
+            last_input_sequence: None,
+            preset: None,
+            pending_drop: None,
+            shader_backup: None,
+            strict: arguments.strict,
+            last_validation: Instant::now(),
+            prev_parameters: None,
+            last_parameters_diff: Instant::now(),
+        };
+        app.update_title();
+        Ok(app)
     }
 
-    pub fn draw(&mut self) -> Result<()> {
-        self.update();
-        self.graphics.render()?;
-        Ok(())
+    pub fn draw(&mut self) -> Result<(), RenderError> {
+        self.update()?;
+        self.graphics.render(&self.parameters)
+    }
+
+    pub fn request_redraw(&self) {
+        self.graphics.request_redraw();
+    }
+
+    pub async fn reinitialize_graphics(mut self, arguments: &Arguments) -> Result<Self> {
+        self.graphics = self
+            .graphics
+            .reinitialize(arguments.gpu.as_deref(), Self::limits(arguments))
+            .await
+            .context("failed to reinitialize graphics")?;
+        self.resize().context("failed to resize the surface")?;
+        Ok(self)
+    }
+
+    pub fn halve_render_texture_size(&mut self) -> bool {
+        self.graphics.halve_render_texture_size()
     }
 
-    fn update(&mut self) {
+    fn update(&mut self) -> Result<()> {
+        if let Some(midi) = &self.midi {
+            midi.poll(&mut self.parameters, &mut self.timing);
+        }
+        self.timing
+            .set_fragment_shader_invocations(self.graphics.fragment_shader_invocations());
+        self.timing
+            .record_gpu_frame_duration(self.graphics.take_gpu_frame_duration());
+        self.update_input_replay();
         let delta_time = self.timing.update(&mut self.parameters);
-        self.camera.update(self.held_keys, delta_time);
+        if let Some(kiosk) = &mut self.kiosk {
+            kiosk.update(&mut self.camera, &mut self.parameters, delta_time);
+        } else if let Some(demo_mode) = &mut self.demo_mode {
+            demo_mode.update(&mut self.camera, &mut self.parameters, delta_time);
+        } else if let Some(transition) = &mut self.camera_transition {
+            self.camera = transition.update(delta_time);
+            if transition.is_finished() {
+                self.camera_transition = None;
+                self.update_title();
+            }
+        } else {
+            self.camera.update(self.held_keys, delta_time);
+        }
         self.parameters.update_camera(&self.camera);
+        self.parameters
+            .update_camera_b(self.camera_poses.first().unwrap_or(&self.camera));
+        self.parameters
+            .update_orbit_target(self.camera.orbit_target());
+        self.update_jitter();
+        // the title shows the camera position, but setting it every frame would be wasteful
+        if self.timing.logged_fps_this_frame() {
+            self.update_title();
+        }
+        if self.graphics.poll_reload() {
+            self.restore_shader_backup_on_error();
+            self.update_title();
+        }
         self.graphics.update_parameters_buffer(&self.parameters);
+        if cfg!(debug_assertions) {
+            self.log_parameter_changes();
+        }
+        self.validate_parameters()
+    }
+
+    const PARAMETERS_DIFF_INTERVAL: Duration = Duration::from_secs(5);
+
+    fn log_parameter_changes(&mut self) {
+        let now = Instant::now();
+        if now - self.last_parameters_diff < Self::PARAMETERS_DIFF_INTERVAL {
+            return;
+        }
+        self.last_parameters_diff = now;
+        if let Some(prev_parameters) = &self.prev_parameters {
+            let changes: Vec<_> = self
+                .parameters
+                .diff(prev_parameters)
+                .into_iter()
+                .map(|(name, delta)| format!("{name} {delta:+}"))
+                .collect();
+            if !changes.is_empty() {
+                eprintln!("changed parameters: {}", changes.join(", "));
+            }
+        }
+        self.prev_parameters = Some(self.parameters);
+    }
+
+    // the halton sequence covers the pixel evenly, the cycle length keeps its values precise
+    const JITTER_CYCLE_LENGTH: u32 = 16;
+
+    fn update_jitter(&mut self) {
+        if !self.parameters.is_jitter_enabled() {
+            return;
+        }
+        self.jitter_index = self.jitter_index % Self::JITTER_CYCLE_LENGTH + 1;
+        // screen positions span 2 units, so half a pixel is 1 / size
+        let (width, height) = self.graphics.render_texture_size();
+        let x = (halton(self.jitter_index, 2) - 0.5) * 2.0 / width as f32;
+        let y = (halton(self.jitter_index, 3) - 0.5) * 2.0 / height as f32;
+        self.parameters.update_jitter([x, y]);
+    }
+
+    const VALIDATION_INTERVAL: Duration = Duration::from_secs(1);
+
+    fn validate_parameters(&mut self) -> Result<()> {
+        let now = Instant::now();
+        if now - self.last_validation < Self::VALIDATION_INTERVAL {
+            return Ok(());
+        }
+        self.last_validation = now;
+        let violations = self.parameters.validate();
+        for violation in &violations {
+            eprintln!("invalid parameter: {violation}");
+        }
+        if self.strict && !violations.is_empty() {
+            bail!("invalid parameters: {}", violations.join(", "));
+        }
+        Ok(())
+    }
+
+    pub fn set_scene_index(&mut self, index: usize) {
+        self.parameters.set_scene_index(index);
+    }
+
+    pub fn save_output(&self, path: &Path) -> Result<()> {
+        self.graphics
+            .save_output(path)
+            .with_context(|| format!("failed to save output to {}", path.display()))
+    }
+
+    pub fn benchmark(&mut self, frames: u32) -> Result<BenchmarkResult> {
+        let mut frame_times = Vec::new();
+        let start = Instant::now();
+        for _ in 0..frames {
+            let frame_start = Instant::now();
+            self.draw().context("failed to draw")?;
+            // without waiting, only the time to submit the frame would be measured
+            self.graphics.wait_for_gpu()?;
+            frame_times.push(frame_start.elapsed().as_secs_f32());
+        }
+        let total = start.elapsed();
+        if frame_times.is_empty() {
+            bail!("the benchmark needs at least one frame");
+        }
+        frame_times.sort_by(f32::total_cmp);
+        Ok(BenchmarkResult {
+            total_ms: total.as_secs_f64() * 1000.0,
+            mean_fps: frames as f32 / total.as_secs_f32(),
+            // the slowest frames have the lowest frame rates
+            min_fps: 1.0 / percentile(&frame_times, 1.0),
+            p99_fps: 1.0 / percentile(&frame_times, 0.99),
+        })
+    }
+
+    pub fn take_screenshot_at_resolution(
+        &self,
+        width: u32,
+        height: u32,
+        path: &Path,
+    ) -> Result<()> {
+        self.graphics
+            .save_render_at_resolution(&self.parameters, width, height, path)
+            .with_context(|| format!("failed to save screenshot to {}", path.display()))
+    }
+
+    const SCREENSHOT_SIZE: (u32, u32) = (3840, 2160);
+
+    fn take_screenshot(&self) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+        let path = PathBuf::from(format!("screenshot_{timestamp}.png"));
+        let (width, height) = Self::SCREENSHOT_SIZE;
+        if let Err(error) = self.take_screenshot_at_resolution(width, height, &path) {
+            eprintln!("{error:?}");
+        } else {
+            eprintln!("saved {}", path.display());
+        }
+    }
+
+    fn save_parameters_snapshot(&self) -> Result<()> {
+        // shared snapshots should tell which GPU they were taken on
+        let adapter = self.graphics.adapter_info_string();
+        let wgsl = format!("// adapter: {adapter}\n{}", self.parameters.to_wgsl_const());
+        write(Self::PARAMETERS_SNAPSHOT_PATH, wgsl)
+            .with_context(|| format!("failed to write {}", Self::PARAMETERS_SNAPSHOT_PATH))
     }
 
     pub fn resize(&mut self) -> Result<()> {
@@ -52,42 +364,318 @@ impl InitializedApp {
     }
 
     pub fn handle_key(&mut self, event: &KeyEvent) {
+        let event = &KeyInput::from(event);
+        if self.kiosk.is_some() {
+            if event.state == ElementState::Pressed && NamedKey::Escape.matches_event(event) {
+                self.exit_requested = true;
+            }
+            return;
+        }
+        self.handle_key_input(event);
+    }
+
+    fn handle_key_input(&mut self, event: &KeyInput) {
+        // the key that starts or stops the recording is left out of it
+        let was_recording = self.input_recorder.is_some();
         self.handle_held_keys(event);
         self.handle_trigger_keys(event);
+        if was_recording && let Some(recorder) = &mut self.input_recorder {
+            recorder.record(InputEventKind::Key(event.clone()));
+        }
+    }
+
+    pub fn start_recording(&mut self) {
+        eprintln!("recording input");
+        self.input_recorder = Some(InputRecorder::start());
+    }
+
+    pub fn stop_recording(&mut self) -> InputSequence {
+        let sequence = self
+            .input_recorder
+            .take()
+            .map(InputRecorder::finish)
+            .unwrap_or_default();
+        eprintln!("recorded {} input events", sequence.events.len());
+        sequence
+    }
+
+    pub fn replay_input_sequence(&mut self, sequence: InputSequence) {
+        eprintln!("replaying {} input events", sequence.events.len());
+        self.input_replay = Some(InputReplay::start(sequence));
+    }
+
+    fn cycle_preset(&mut self) {
+        let preset = self
+            .preset
+            .map_or_else(FractalPreset::first, FractalPreset::next);
+        self.parameters.apply_preset(preset);
+        self.camera.apply_preset(preset);
+        self.preset = Some(preset);
+        eprintln!("preset: {preset:?}");
+        self.update_title();
+    }
+
+    fn toggle_recording(&mut self) {
+        if self.input_recorder.is_some() {
+            self.last_input_sequence = Some(self.stop_recording());
+        } else {
+            self.start_recording();
+        }
+    }
+
+    fn replay_last_input_sequence(&mut self) {
+        if self.input_recorder.is_some() {
+            // the replayed events would be recorded again
+            eprintln!("cannot replay input while recording");
+            return;
+        }
+        if self.input_replay.is_some() {
+            // e.g. a recorded press of this key
+            eprintln!("already replaying input");
+            return;
+        }
+        match self.last_input_sequence.clone() {
+            Some(sequence) => self.replay_input_sequence(sequence),
+            None => eprintln!("no input has been recorded yet"),
+        }
+    }
+
+    fn update_input_replay(&mut self) {
+        let Some(replay) = &mut self.input_replay else {
+            return;
+        };
+        let events = replay.due_events();
+        let is_finished = replay.is_finished();
+        for event in events {
+            match event {
+                InputEventKind::Key(key_input) => self.handle_key_input(&key_input),
+                InputEventKind::MouseWheel(delta) => self.handle_mouse_wheel(delta),
+            }
+        }
+        if is_finished {
+            self.input_replay = None;
+            eprintln!("finished replaying input");
+        }
     }
 
-    fn handle_trigger_keys(&mut self, event: &KeyEvent) {
+    fn handle_trigger_keys(&mut self, event: &KeyInput) {
         if event.state != ElementState::Pressed {
             return;
         }
+        if self.demo_mode.is_some() {
+            // repeats of the key that started the demo should not stop it
+            if !event.repeat {
+                self.demo_mode = None;
+            }
+            return;
+        }
         macro_rules! handle_keys {
             ($($key:expr => $body:stmt),* $(,)?) => {
                 $(
-                    if event.logical_key == $key {
+                    if $key.matches_event(event) {
                         $body
                         return;
                     }
                 )*
             };
         }
+        if self.held_keys.is_control_pressed() {
+            handle_keys!(
+                NamedKey::Home => self.camera.set_position_spherical(1.0, Rad::zero(), Rad::zero()),
+                "t" => self.parameters.toggle_show_orbit_target(),
+                "n" => self.parameters.update_normal_epsilon(0.5),
+                "N" => self.parameters.update_normal_epsilon(2.0),
+                "f" => {
+                    self.timing.toggle_step_mode();
+                    self.update_title();
+                },
+                "S" => {
+                    self.camera.toggle_speed_mode();
+                    self.update_title();
+                },
+                "T" => {
+                    self.timing.toggle_time_mode();
+                    self.update_title();
+                },
+                "r" => self.toggle_recording(),
+                "e" => self.replay_last_input_sequence(),
+                "p" => self.cycle_preset(),
+                "P" => {
+                    self.camera.toggle_unconstrained_pitch();
+                    self.update_title();
+                },
+                "v" => {
+                    if let Err(error) = self.graphics.toggle_vsync() {
+                        eprintln!("{error:?}");
+                    }
+                    eprintln!("present mode: {:?}", self.graphics.present_mode());
+                    self.update_title();
+                },
+                "o" => {
+                    self.camera.toggle_orbit_distance_lock();
+                    self.update_title();
+                },
+                "b" => {
+                    self.camera.toggle_position_limit();
+                    self.update_title();
+                },
+                "d" => self.start_demo_mode(),
+                "R" => {
+                    self.timing.clear_session_stats();
+                    eprintln!("cleared session stats");
+                },
+                "C" => {
+                    if let Err(error) = self.save_parameters_snapshot() {
+                        eprintln!("{error:?}");
+                    } else {
+                        eprintln!("saved {}", Self::PARAMETERS_SNAPSHOT_PATH);
+                    }
+                },
+            );
+        }
+        if self.held_keys.is_alt_pressed() {
+            handle_keys!(
+                "r" => self.parameters.randomize_seed(),
+                "c" => self.parameters.cycle_scanline_strength(),
+                "x" => self.parameters.toggle_ssr(),
+                "t" => {
+                    self.timing.stop_time_b();
+                    self.update_title();
+                },
+            );
+        }
+        if self.held_keys.is_shift_pressed() {
+            handle_keys!(
+                NamedKey::Space => self.teleport_to_camera_pose(),
+            );
+        }
         handle_keys!(
             NamedKey::Escape => self.graphics.ungrab_cursor(),
+            KeyCode::NumpadAdd => self.parameters.update_fractal_power(1.0),
+            KeyCode::NumpadSubtract => self.parameters.update_fractal_power(-1.0),
             "+" => self.parameters.update_num_iterations(1),
             "-" => self.parameters.update_num_iterations(-1),
             "n" => self.parameters.update_scene_index(1),
             "b" => self.parameters.update_scene_index(-1),
-            "o" => self.camera.reset_orbit_speed(),
-            "p" => self.camera.toggle_lock_pitch(),
-            "l" => self.camera.cycle_lock_yaw_mode(false),
-            "L" => self.camera.cycle_lock_yaw_mode(true),
-            "t" => self.timing.stop_time(),
-            "r" => self.graphics.try_reload(),
+            "N" => self.parameters.toggle_split_screen(),
+            "o" => {
+                self.camera.reset_orbit_speed();
+                self.update_title();
+            },
+            "P" => self.take_screenshot(),
+            "p" => {
+                self.camera.toggle_lock_pitch();
+                self.update_title();
+            },
+            "l" => {
+                self.camera.cycle_lock_yaw_mode(false);
+                self.update_title();
+            },
+            "L" => {
+                self.camera.cycle_lock_yaw_mode(true);
+                self.update_title();
+            },
+            "g" => self.camera.toggle_auto_correct_roll(),
+            "G" => self.camera.toggle_smoothing(),
+            "t" => {
+                self.timing.stop_time();
+                self.update_title();
+            },
+            "T" => {
+                self.timing.reset_time_factor();
+                self.update_title();
+            },
+            "D" => self.parameters.toggle_dither(),
+            "B" => self.parameters.toggle_bloom(),
+            "X" => self.parameters.cycle_chromatic_aberration(),
+            "S" => self.parameters.cycle_sharpen(),
+            "F" => self.parameters.cycle_film_grain(),
+            "A" => self.parameters.toggle_jitter(),
+            "c" => self.parameters.cycle_background_gradient(),
+            "C" => self.store_camera_pose(),
+            "V" => self.parameters.toggle_blend_views(),
+            "i" => self.parameters.cycle_interior_mode(),
+            NamedKey::Backspace => {
+                self.parameters.cycle_debug_mode();
+                self.update_title();
+            },
+            "h" => {
+                if let Err(error) = self.graphics.toggle_hdr() {
+                    eprintln!("{error:?}");
+                }
+                self.update_title();
+            },
+            "]" => self.parameters.update_mandelbox_scale(0.1),
+            "[" => self.parameters.update_mandelbox_scale(-0.1),
+            "}" => self.parameters.update_mandelbox_fold_radius(0.1),
+            "{" => self.parameters.update_mandelbox_fold_radius(-0.1),
+            "R" => self.parameters.increment_seed(),
+            "r" => {
+                self.graphics.try_reload();
+                self.update_title();
+            },
             ">" => self.graphics.update_render_texture_size(1),
             "<" => self.graphics.update_render_texture_size(-1),
+            "U" => {
+                let upscale_mode = self.graphics.cycle_upscale_mode();
+                eprintln!("upscale mode: {upscale_mode:?}");
+            },
         );
     }
 
-    fn handle_held_keys(&mut self, event: &KeyEvent) {
+    fn start_demo_mode(&mut self) {
+        self.graphics.ungrab_cursor();
+        self.demo_mode = Some(DemoMode::start(&self.camera, &self.parameters));
+        eprintln!("Demo mode: press any key to exit");
+    }
+
+    fn store_camera_pose(&mut self) {
+        match self.camera_poses.first_mut() {
+            Some(pose) => *pose = self.camera.clone(),
+            None => self.camera_poses.push(self.camera.clone()),
+        }
+        let bookmark = CameraSnapshot::from(&self.camera).to_bookmark();
+        if let Err(error) = write(CameraSnapshot::BOOKMARK_PATH, bookmark) {
+            eprintln!("failed to write {}: {error}", CameraSnapshot::BOOKMARK_PATH);
+        }
+    }
+
+    // the stored camera of the last session, with the other settings taken from the given one
+    fn load_camera_bookmark(camera: &Camera) -> Option<Camera> {
+        let path = Path::new(CameraSnapshot::BOOKMARK_PATH);
+        if !path.exists() {
+            return None;
+        }
+        let snapshot = fs::read_to_string(path)
+            .context("failed to read")
+            .and_then(|source| CameraSnapshot::parse_bookmark(&source));
+        match snapshot {
+            Result::Ok(snapshot) => {
+                let mut pose = camera.clone();
+                pose.restore_from_snapshot(&snapshot);
+                Some(pose)
+            }
+            Err(error) => {
+                eprintln!("ignoring {}: {error:#}", path.display());
+                None
+            }
+        }
+    }
+
+    fn teleport_to_camera_pose(&mut self) {
+        if let Some(pose) = self.camera_poses.first() {
+            self.camera_transition =
+                Some(CameraTransition::start(self.camera.clone(), pose.clone()));
+        }
+    }
+
+    fn handle_held_keys(&mut self, event: &KeyInput) {
+        let now = Instant::now();
+        let pressed = event.state.is_pressed();
+        if pressed && !event.repeat {
+            // e.g. alt + R is not a tap of alt
+            self.held_key_times.interrupt();
+        }
         macro_rules! match_key {
             ($($key:expr => $held_key:expr,)* else => $default:expr $(,)?) => {
                 $(if event.logical_key == $key { $held_key } else )*
@@ -105,34 +693,185 @@ impl InitializedApp {
             NamedKey::ArrowUp => HeldKeys::PitchUp,
             NamedKey::ArrowRight => HeldKeys::YawRight,
             NamedKey::ArrowLeft => HeldKeys::YawLeft,
+            "," => HeldKeys::RollClockwise,
+            "." => HeldKeys::RollCounterClockwise,
             NamedKey::Shift => HeldKeys::Shift,
             NamedKey::Control => HeldKeys::Control,
+            NamedKey::Alt => HeldKeys::Alt,
             else => return,
         };
-        self.held_keys.set(held_key, event.state.is_pressed());
+        if !pressed && self.held_key_times.is_tap(held_key, now) {
+            self.handle_tap(held_key);
+        }
+        self.held_key_times.set(held_key, pressed, now);
+        self.held_keys.set(held_key, pressed);
+    }
+
+    // for held keys that do something else when only tapped
+    fn handle_tap(&mut self, held_key: HeldKeys) {
+        if held_key == HeldKeys::Alt {
+            self.camera.toggle_fine_movement();
+            self.update_title();
+        }
+    }
+
+    fn update_title(&self) {
+        let mut status = self.camera.status_string();
+        if let StepMode::FixedStep(fps) = self.timing.step_mode() {
+            status.push_str(&format!(" [fixed step: {fps} FPS]"));
+        }
+        if let TimeMode::Cyclic(period) = self.timing.time_mode() {
+            status.push_str(&format!(" [time wraps every {period:.1} s]"));
+        }
+        let time_factor = self.timing.time_factor();
+        if time_factor != 1.0 {
+            status.push_str(&format!(" [×{time_factor:.2}]"));
+        }
+        let time_factor_b = self.timing.time_factor_b();
+        if time_factor_b != 1.0 {
+            status.push_str(&format!(" [shapes ×{time_factor_b:.2}]"));
+        }
+        if self.graphics.is_hdr() {
+            status.push_str(" [HDR]");
+        }
+        if !self.graphics.is_vsync() {
+            status.push_str(" [no V-Sync]");
+        }
+        if self.graphics.is_reloading() {
+            status.push_str(" [loading shader…]");
+        }
+        status.push_str(&format!(" [shader {}]", self.graphics.shader_short_hash()));
+        if let Some(name) = self.parameters.debug_mode_name() {
+            // tells whether a reload actually replaced the shader that is being debugged
+            let shader_age = self.graphics.shader_age().as_secs();
+            status.push_str(&format!(
+                " [debug: {name}, shader compiled {shader_age} s ago]"
+            ));
+        }
+        self.graphics.set_title_status(&status);
+    }
+
+    const DROP_CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(5);
+
+    // a file has to be dropped twice, so accidental drops do not overwrite the shader source
+    pub fn handle_drop(&mut self, path: &Path) -> Result<()> {
+        if self.kiosk.is_some() {
+            return Ok(());
+        }
+        if path.extension().is_none_or(|extension| extension != "wgsl") {
+            bail!(
+                "only WGSL files can replace the fragment shader, got {}",
+                path.display()
+            );
+        }
+        if !cfg!(debug_assertions) {
+            bail!("only debug builds load the fragment shader from disk");
+        }
+        let target = ReloadableGraphics::fragment_shader_path();
+        let now = Instant::now();
+        let confirmed = self.pending_drop.take().is_some_and(|(pending, time)| {
+            pending == path && now - time < Self::DROP_CONFIRMATION_TIMEOUT
+        });
+        if !confirmed {
+            eprintln!(
+                "drop {} again within {} s to replace {}",
+                path.display(),
+                Self::DROP_CONFIRMATION_TIMEOUT.as_secs(),
+                target.display()
+            );
+            self.pending_drop = Some((path.to_owned(), now));
+            return Ok(());
+        }
+        let backup = fs::read_to_string(&target)
+            .with_context(|| format!("failed to back up {}", target.display()))?;
+        fs::copy(path, &target).with_context(|| {
+            format!("failed to copy {} to {}", path.display(), target.display())
+        })?;
+        self.shader_backup = Some(backup);
+        self.graphics.try_reload();
+        self.restore_shader_backup_on_error();
+        self.update_title();
+        Ok(())
+    }
+
+    fn restore_shader_backup_on_error(&mut self) {
+        if self.graphics.is_reloading() {
+            return;
+        }
+        let Some(backup) = self.shader_backup.take() else {
+            return;
+        };
+        if !self.graphics.has_shader_error() {
+            return;
+        }
+        let target = ReloadableGraphics::fragment_shader_path();
+        if let Err(error) = write(&target, backup) {
+            eprintln!("failed to restore {}: {error}", target.display());
+        } else {
+            eprintln!(
+                "the dropped shader failed to compile, restored {}",
+                target.display()
+            );
+        }
+    }
+
+    pub fn shared_window(&self) -> Option<Arc<Window>> {
+        self.graphics.shared_window()
+    }
+
+    // only set in kiosk mode, where escape exits instead of releasing the cursor
+    pub fn is_exit_requested(&self) -> bool {
+        self.exit_requested
     }
 
     pub fn handle_mouse(&mut self, button: MouseButton, state: ElementState) {
+        if self.kiosk.is_some() {
+            return;
+        }
         if button == MouseButton::Left && state == ElementState::Pressed {
             self.graphics.grab_cursor();
         }
     }
 
     pub fn handle_mouse_wheel(&mut self, delta: MouseScrollDelta) {
+        if self.kiosk.is_some() {
+            return;
+        }
+        if let Some(recorder) = &mut self.input_recorder {
+            recorder.record(InputEventKind::MouseWheel(delta));
+        }
+        // e.g. alt + scroll is not a tap of alt
+        self.held_key_times.interrupt();
         const LINE_FACTOR: f32 = 0.5;
         let (mut x, mut y) = match delta {
             MouseScrollDelta::LineDelta(x, y) => (x * LINE_FACTOR, y * LINE_FACTOR),
             MouseScrollDelta::PixelDelta(PhysicalPosition { x, y }) => (x as f32, y as f32),
         };
+        if self.held_keys.is_shift_pressed() && self.held_keys.is_control_pressed() {
+            self.camera.update_vertical_speed_multiplier(y * 0.05);
+            self.update_title();
+            return;
+        }
+        if self.held_keys.is_shift_pressed() && self.graphics.is_cursor_grabbed() {
+            self.parameters.update_step_factor(y * 0.05);
+            return;
+        }
         if self.held_keys.is_shift_pressed() {
             x += y;
             y = 0.0;
         }
-        if self.held_keys.is_control_pressed() {
+        if self.held_keys.is_control_pressed() && self.held_keys.is_alt_pressed() {
+            self.timing.update_time_factor_b(y);
+            self.update_title();
+        } else if self.held_keys.is_control_pressed() {
             self.timing.update_time_factor(y);
+            self.update_title();
+        } else if self.held_keys.is_alt_pressed() {
+            self.parameters.update_bloom_threshold(y * 0.05);
         } else {
             self.camera.update_orbit_speed(x);
             self.camera.update_speed(y);
+            self.update_title();
         }
     }
 
@@ -142,7 +881,18 @@ impl InitializedApp {
         }
     }
 
+    pub fn handle_theme_changed(&mut self, theme: Theme) {
+        self.parameters.set_theme(theme == Theme::Dark);
+    }
+
+    pub fn handle_occluded(&mut self, occluded: bool) {
+        self.graphics.set_occluded(occluded);
+    }
+
     pub fn handle_cursor_movement(&mut self, position: PhysicalPosition<f64>) -> Result<()> {
+        if self.kiosk.is_some() {
+            return Ok(());
+        }
         let delta = self.graphics.move_cursor(position)?;
         if let Some(delta) = delta {
             let yaw = delta.x as f32;
@@ -152,3 +902,25 @@ impl InitializedApp {
         Ok(())
     }
 }
+
+trait KeyBinding {
+    fn matches_event(self, event: &KeyInput) -> bool;
+}
+
+impl KeyBinding for &str {
+    fn matches_event(self, event: &KeyInput) -> bool {
+        event.logical_key == self
+    }
+}
+
+impl KeyBinding for NamedKey {
+    fn matches_event(self, event: &KeyInput) -> bool {
+        event.logical_key == self
+    }
+}
+
+impl KeyBinding for KeyCode {
+    fn matches_event(self, event: &KeyInput) -> bool {
+        event.physical_key == PhysicalKey::Code(self)
+    }
+}
@@ -0,0 +1,146 @@
+use std::time::{Duration, Instant};
+use winit::{
+    event::{ElementState, KeyEvent, MouseScrollDelta},
+    keyboard::{Key, PhysicalKey},
+};
+
+// the parts of a KeyEvent the app reacts to, since winit does not allow constructing one
+#[derive(Debug, Clone)]
+pub struct KeyInput {
+    pub logical_key: Key,
+    pub physical_key: PhysicalKey,
+    pub state: ElementState,
+    pub repeat: bool,
+}
+
+impl From<&KeyEvent> for KeyInput {
+    fn from(event: &KeyEvent) -> Self {
+        Self {
+            logical_key: event.logical_key.clone(),
+            physical_key: event.physical_key,
+            state: event.state,
+            repeat: event.repeat,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum InputEventKind {
+    Key(KeyInput),
+    MouseWheel(MouseScrollDelta),
+}
+
+#[derive(Debug, Clone)]
+pub struct InputEvent {
+    // since the recording started
+    pub time: Duration,
+    pub kind: InputEventKind,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct InputSequence {
+    pub events: Vec<InputEvent>,
+}
+
+#[derive(Debug)]
+pub struct InputRecorder {
+    start_time: Instant,
+    sequence: InputSequence,
+}
+
+impl InputRecorder {
+    pub fn start() -> Self {
+        Self {
+            start_time: Instant::now(),
+            sequence: InputSequence::default(),
+        }
+    }
+
+    pub fn record(&mut self, kind: InputEventKind) {
+        self.sequence.events.push(InputEvent {
+            time: self.start_time.elapsed(),
+            kind,
+        });
+    }
+
+    pub fn finish(self) -> InputSequence {
+        self.sequence
+    }
+}
+
+// polled every frame instead of sleeping, so rendering goes on between the events
+#[derive(Debug)]
+pub struct InputReplay {
+    start_time: Instant,
+    sequence: InputSequence,
+    next_index: usize,
+}
+
+impl InputReplay {
+    pub fn start(sequence: InputSequence) -> Self {
+        Self {
+            start_time: Instant::now(),
+            sequence,
+            next_index: 0,
+        }
+    }
+
+    pub fn due_events(&mut self) -> Vec<InputEventKind> {
+        let elapsed = self.start_time.elapsed();
+        let remaining = &self.sequence.events[self.next_index..];
+        let due_count = remaining
+            .iter()
+            .take_while(|event| event.time <= elapsed)
+            .count();
+        self.next_index += due_count;
+        remaining[..due_count]
+            .iter()
+            .map(|event| event.kind.clone())
+            .collect()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next_index == self.sequence.events.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scroll_at(secs: u64, lines: f32) -> InputEvent {
+        InputEvent {
+            time: Duration::from_secs(secs),
+            kind: InputEventKind::MouseWheel(MouseScrollDelta::LineDelta(0.0, lines)),
+        }
+    }
+
+    fn scrolled_lines(events: Vec<InputEventKind>) -> Vec<f32> {
+        events
+            .into_iter()
+            .map(|kind| match kind {
+                InputEventKind::MouseWheel(MouseScrollDelta::LineDelta(_, lines)) => lines,
+                kind => panic!("unexpected event {kind:?}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn events_are_replayed_once_they_are_due() {
+        let sequence = InputSequence {
+            events: vec![scroll_at(0, 1.0), scroll_at(10, 2.0), scroll_at(20, 3.0)],
+        };
+        let mut replay = InputReplay::start(sequence);
+        assert_eq!(scrolled_lines(replay.due_events()), [1.0]);
+        assert!(!replay.is_finished());
+        // moving the start back simulates the time passing between the polls
+        replay.start_time -= Duration::from_secs(15);
+        assert_eq!(scrolled_lines(replay.due_events()), [2.0]);
+        assert_eq!(scrolled_lines(replay.due_events()), []);
+        assert!(!replay.is_finished());
+        replay.start_time -= Duration::from_secs(10);
+        assert_eq!(scrolled_lines(replay.due_events()), [3.0]);
+        assert!(replay.is_finished());
+        assert_eq!(scrolled_lines(replay.due_events()), []);
+    }
+}
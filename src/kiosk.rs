@@ -0,0 +1,65 @@
+use crate::{camera::Camera, parameters::Parameters};
+use cgmath::{Rad, Vector3, Zero};
+use std::{f32::consts::FRAC_PI_3, time::Duration};
+
+// unattended display, unlike the demo mode it keeps running until the app exits
+#[derive(Debug)]
+pub struct KioskController {
+    stops: Vec<KioskStop>,
+    stop_index: usize,
+    // since arriving at the current stop
+    time: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct KioskStop {
+    scene_index: u32,
+    dwell_seconds: f32,
+    radius: f32,
+    polar: Rad<f32>,
+    azimuth: Rad<f32>,
+}
+
+impl KioskController {
+    const ORBIT_ANGLE_PER_SECOND: Rad<f32> = Rad(0.05);
+    const SECONDS_PER_SCENE: f32 = 30.0;
+
+    pub fn start() -> Self {
+        Self {
+            stops: Self::default_stops(),
+            stop_index: 0,
+            time: 0.0,
+        }
+    }
+
+    // every scene in order, each viewed from a different side
+    fn default_stops() -> Vec<KioskStop> {
+        (0..Parameters::NUM_SCENES)
+            .map(|scene_index| KioskStop {
+                scene_index,
+                dwell_seconds: Self::SECONDS_PER_SCENE,
+                radius: 2.0,
+                polar: Rad(FRAC_PI_3),
+                azimuth: Rad(scene_index as f32),
+            })
+            .collect()
+    }
+
+    pub fn update(
+        &mut self,
+        camera: &mut Camera,
+        parameters: &mut Parameters,
+        delta_time: Duration,
+    ) {
+        self.time += delta_time.as_secs_f32();
+        while self.time >= self.stops[self.stop_index].dwell_seconds {
+            self.time -= self.stops[self.stop_index].dwell_seconds;
+            self.stop_index = (self.stop_index + 1) % self.stops.len();
+        }
+        let stop = self.stops[self.stop_index];
+        let azimuth = stop.azimuth + Self::ORBIT_ANGLE_PER_SECOND * self.time;
+        camera.set_position_spherical(stop.radius, stop.polar, azimuth);
+        camera.look_at(Vector3::zero());
+        parameters.set_scene_index(stop.scene_index as usize);
+    }
+}
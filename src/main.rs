@@ -1,22 +1,57 @@
-use crate::app::App;
+use crate::{app::App, arguments::Arguments, persistent_graphics::PersistentGraphics};
+use pollster::block_on;
 use winit::event_loop::EventLoop;
 
 mod app;
+mod arguments;
+mod benchmark;
 mod blit_graphics;
+mod bloom_graphics;
 mod camera;
+mod demo_mode;
+mod gpu_timer;
 mod graphics;
+mod headless;
 mod held_keys;
 mod initialized_app;
+mod input_sequence;
+mod kiosk;
+mod midi;
 mod parameters;
 mod persistent_graphics;
+mod pipeline_statistics;
+mod preprocessor;
+mod presets;
+mod query_readback;
 mod reloadable_graphics;
+mod render_error;
 mod render_texture_config;
+mod ssr_graphics;
+mod submit_profile;
 mod timing;
+mod upscale_graphics;
 mod utils;
 
 fn main() {
+    let arguments = Arguments::parse().expect("failed to parse arguments");
+    if arguments.gpu_info {
+        let info = block_on(PersistentGraphics::query_adapter_info_string(
+            arguments.gpu.as_deref(),
+        ))
+        .expect("failed to query the adapter");
+        println!("{info}");
+        return;
+    }
+    if let Some(frames) = arguments.benchmark {
+        benchmark::run(&arguments, frames).expect("benchmark failed");
+        return;
+    }
+    if arguments.headless {
+        headless::run(&arguments).expect("headless rendering failed");
+        return;
+    }
     let event_loop = EventLoop::new().unwrap();
     event_loop
-        .run_app(&mut App::default())
+        .run_app(&mut App::new(arguments))
         .expect("event loop error");
 }
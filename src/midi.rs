@@ -0,0 +1,203 @@
+use crate::{parameters::Parameters, timing::Timing, utils::parse_key_value_lines};
+use anyhow::{Context, Result, bail};
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::read_to_string,
+    io::ErrorKind,
+    path::Path,
+    sync::mpsc::{Receiver, Sender, channel},
+};
+
+#[derive(Debug, Clone, Copy)]
+pub enum MidiTarget {
+    TimeFactor,
+    FractalPower,
+    BloomThreshold,
+    StepFactor,
+}
+
+impl MidiTarget {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "time_factor" => Some(Self::TimeFactor),
+            "fractal_power" => Some(Self::FractalPower),
+            "bloom_threshold" => Some(Self::BloomThreshold),
+            "step_factor" => Some(Self::StepFactor),
+            _ => None,
+        }
+    }
+
+    const MAX_TIME_FACTOR: f32 = 2.0;
+
+    pub fn apply(self, fraction: f32, parameters: &mut Parameters, timing: &mut Timing) {
+        match self {
+            Self::TimeFactor => timing.set_time_factor(fraction * Self::MAX_TIME_FACTOR),
+            Self::FractalPower => parameters.set_fractal_power_fraction(fraction),
+            Self::BloomThreshold => parameters.set_bloom_threshold_fraction(fraction),
+            Self::StepFactor => parameters.set_step_factor_fraction(fraction),
+        }
+    }
+}
+
+// used when there is no mapping file in the working directory
+const BUILT_IN_MAPPING: &str = include_str!("../midi_mapping.toml");
+
+// the mapping file consists of `<control change number> = "<target>"` lines
+fn parse_mapping(source: &str) -> Result<HashMap<u8, MidiTarget>> {
+    let mut mapping = HashMap::new();
+    for (line_number, control, target) in parse_key_value_lines(source)? {
+        let control = control
+            .parse()
+            .with_context(|| format!("line {line_number}: invalid control change number"))?;
+        let target = target.trim_matches('"');
+        let Some(target) = MidiTarget::from_name(target) else {
+            bail!("line {line_number}: unknown target {target:?}");
+        };
+        mapping.insert(control, target);
+    }
+    Ok(mapping)
+}
+
+#[cfg(feature = "midi")]
+type Connection = midir::MidiInputConnection<()>;
+#[cfg(not(feature = "midi"))]
+type Connection = ();
+
+pub struct MidiInput {
+    mapping: HashMap<u8, MidiTarget>,
+    receiver: Receiver<Vec<u8>>,
+    _connection: Connection,
+}
+
+impl fmt::Debug for MidiInput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MidiInput")
+            .field("mapping", &self.mapping)
+            .finish_non_exhaustive()
+    }
+}
+
+impl MidiInput {
+    pub const MAPPING_PATH: &'static str = "midi_mapping.toml";
+
+    pub fn connect(mapping_path: &Path) -> Result<Option<Self>> {
+        let mapping = Self::read_mapping(mapping_path)?;
+        let (sender, receiver) = channel();
+        let Some(connection) = Self::connect_to_first_port(sender)? else {
+            return Ok(None);
+        };
+        Ok(Some(Self {
+            mapping,
+            receiver,
+            _connection: connection,
+        }))
+    }
+
+    // the path is relative to the working directory, which is not the repository
+    // when running an installed binary
+    fn read_mapping(mapping_path: &Path) -> Result<HashMap<u8, MidiTarget>> {
+        let display_path = mapping_path.display();
+        let source = match read_to_string(mapping_path) {
+            Ok(source) => source,
+            Err(error) if error.kind() == ErrorKind::NotFound => {
+                eprintln!("{display_path} not found, using the built-in MIDI mapping");
+                return parse_mapping(BUILT_IN_MAPPING)
+                    .context("failed to parse the built-in MIDI mapping");
+            }
+            Err(error) => {
+                return Err(error).with_context(|| format!("failed to read {display_path}"));
+            }
+        };
+        parse_mapping(&source).with_context(|| format!("failed to parse {display_path}"))
+    }
+
+    #[cfg(feature = "midi")]
+    fn connect_to_first_port(sender: Sender<Vec<u8>>) -> Result<Option<Connection>> {
+        let input = midir::MidiInput::new(crate::persistent_graphics::PersistentGraphics::TITLE)
+            .map_err(|error| anyhow::anyhow!("failed to initialize MIDI input: {error}"))?;
+        let Some(port) = input.ports().into_iter().next() else {
+            return Ok(None);
+        };
+        let connection = input
+            .connect(
+                &port,
+                "fractals_input",
+                move |_timestamp, message, _| {
+                    // the receiver is only gone while the app shuts down
+                    let _ = sender.send(message.to_vec());
+                },
+                (),
+            )
+            .map_err(|error| anyhow::anyhow!("failed to connect to MIDI input: {error}"))?;
+        Ok(Some(connection))
+    }
+
+    #[cfg(not(feature = "midi"))]
+    fn connect_to_first_port(_sender: Sender<Vec<u8>>) -> Result<Option<Connection>> {
+        eprintln!("built without the midi feature, ignoring MIDI input");
+        Ok(None)
+    }
+
+    pub fn poll(&self, parameters: &mut Parameters, timing: &mut Timing) {
+        const CONTROL_CHANGE: u8 = 0xB0;
+        const MAX_VALUE: f32 = 127.0;
+        for message in self.receiver.try_iter() {
+            let &[status, control, value] = message.as_slice() else {
+                continue;
+            };
+            if status & 0xF0 != CONTROL_CHANGE {
+                continue;
+            }
+            if let Some(target) = self.mapping.get(&control) {
+                target.apply(value as f32 / MAX_VALUE, parameters, timing);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn targets(source: &str) -> Vec<(u8, String)> {
+        let mut targets: Vec<_> = parse_mapping(source)
+            .unwrap()
+            .into_iter()
+            .map(|(control, target)| (control, format!("{target:?}")))
+            .collect();
+        targets.sort();
+        targets
+    }
+
+    #[test]
+    fn comments_and_blank_lines_are_ignored() {
+        let source = "# a comment\n\n7 = \"step_factor\" # trailing comment\n";
+        assert_eq!(targets(source), [(7, "StepFactor".to_string())]);
+    }
+
+    #[test]
+    fn invalid_control_change_numbers_fail() {
+        let error = parse_mapping("1 = \"time_factor\"\n256 = \"fractal_power\"\n").unwrap_err();
+        assert!(
+            format!("{error:#}").contains("line 2: invalid control change number"),
+            "{error:#}"
+        );
+    }
+
+    #[test]
+    fn unknown_targets_fail() {
+        let error = parse_mapping("3 = \"fog_density\"\n").unwrap_err();
+        assert!(
+            format!("{error:#}").contains("line 1: unknown target \"fog_density\""),
+            "{error:#}"
+        );
+    }
+
+    #[test]
+    fn missing_mapping_files_fall_back_to_the_built_in_mapping() {
+        let mapping = MidiInput::read_mapping(Path::new("missing_midi_mapping.toml")).unwrap();
+        assert_eq!(mapping.len(), 4);
+        assert!(matches!(mapping[&1], MidiTarget::TimeFactor));
+    }
+}
@@ -1,9 +1,12 @@
-use crate::camera::Camera;
+use crate::{camera::Camera, presets::FractalPreset};
 use bytemuck::{Pod, Zeroable};
-use cgmath::Matrix;
-use std::cmp::min;
+use cgmath::{Matrix, Vector3};
+use std::{
+    cmp::min,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
-#[derive(Debug, Default, Clone, Copy, Pod, Zeroable)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
 #[repr(C)]
 pub struct Parameters {
     camera_matrix: [[f32; 4]; 4],
@@ -11,7 +14,75 @@ pub struct Parameters {
     time: f32,
     num_iterations: u32,
     scene_index: u32,
-    padding: [u8; 12],
+    fractal_power: f32,
+    dither_enabled: u32,
+    bloom_enabled: u32,
+    bloom_threshold: f32,
+    bloom_strength: f32,
+    viewport_count: u32,
+    step_factor: f32,
+    chromatic_aberration: f32,
+    mandelbox_scale: f32,
+    mandelbox_fold_radius: f32,
+    padding_0: [u8; 4],
+    background_color_top: [f32; 3],
+    padding_1: [u8; 4],
+    background_color_bottom: [f32; 3],
+    interior_mode: u32,
+    orbit_target: [f32; 3],
+    show_orbit_target: u32,
+    normal_epsilon: f32,
+    padding_2: [u8; 12],
+    camera_matrix_b: [[f32; 4]; 4],
+    blend_views: f32,
+    padding_3: [u8; 4],
+    jitter: [f32; 2],
+    jitter_enabled: u32,
+    wall_time: f32,
+    sharpen_strength: f32,
+    film_grain: f32,
+    seed: u32,
+    debug_mode: u32,
+    letterbox_scale: [f32; 2],
+    scanline_strength: f32,
+    ssr_strength: f32,
+    // a second animation channel with its own time factor
+    time_b: f32,
+    padding_4: [u8; 4],
+}
+
+// WGSL rounds the size of uniform structs up to their 16 byte alignment,
+// so a field added without padding would shift the buffer size away from the shader's
+const _: () = assert!(size_of::<Parameters>().is_multiple_of(16));
+// the smallest max_uniform_buffer_binding_size wgpu allows (for downlevel adapters)
+const _: () = assert!(size_of::<Parameters>() <= 16 << 10);
+
+// the per-frame part of Parameters, sent as push constants where supported
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+pub struct FastParameters {
+    camera_matrix: [[f32; 4]; 4],
+    time: f32,
+    padding_0: [u8; 12],
+}
+
+impl Default for Parameters {
+    fn default() -> Self {
+        Self {
+            fractal_power: 8.0,
+            viewport_count: 1,
+            step_factor: 1.0,
+            // zero iterations, which only showed the bounding shapes, are below the clamped range
+            num_iterations: Self::MIN_NUM_ITERATIONS,
+            normal_epsilon: 0.0001,
+            mandelbox_scale: Self::CANONICAL_MANDELBOX_SCALE,
+            mandelbox_fold_radius: Self::CANONICAL_MANDELBOX_FOLD_RADIUS,
+            bloom_threshold: 0.8,
+            bloom_strength: 0.5,
+            letterbox_scale: [1.0, 1.0],
+            ..Zeroable::zeroed()
+        }
+    }
 }
 
 impl Parameters {
@@ -20,22 +91,749 @@ impl Parameters {
         self.aspect_scale = [width as f32 / min, height as f32 / min];
     }
 
+    // the fraction of the surface that the image covers, the rest is left black
+    pub fn update_letterbox(&mut self, surface_width: u32, surface_height: u32) {
+        let [image_x, image_y] = self.aspect_scale;
+        let surface_aspect = surface_width as f32 / surface_height as f32;
+        let image_aspect = image_x / image_y;
+        self.letterbox_scale = if image_aspect > surface_aspect {
+            [1.0, surface_aspect / image_aspect]
+        } else {
+            [image_aspect / surface_aspect, 1.0]
+        };
+    }
+
     pub fn update_camera(&mut self, camera: &Camera) {
         self.camera_matrix = *camera.to_matrix().transpose().as_ref();
     }
 
+    pub fn fast_parameters(&self) -> FastParameters {
+        FastParameters {
+            camera_matrix: self.camera_matrix,
+            time: self.time,
+            ..Zeroable::zeroed()
+        }
+    }
+
+    pub fn update_camera_b(&mut self, camera: &Camera) {
+        self.camera_matrix_b = *camera.to_matrix().transpose().as_ref();
+    }
+
+    pub fn toggle_blend_views(&mut self) {
+        self.blend_views = if self.blend_views == 0.0 { 0.5 } else { 0.0 };
+    }
+
+    pub fn is_jitter_enabled(&self) -> bool {
+        self.jitter_enabled != 0
+    }
+
+    pub fn toggle_jitter(&mut self) {
+        self.jitter_enabled = u32::from(!self.is_jitter_enabled());
+    }
+
+    pub fn update_jitter(&mut self, jitter: [f32; 2]) {
+        self.jitter = jitter;
+    }
+
+    pub fn update_orbit_target(&mut self, point: Vector3<f32>) {
+        self.orbit_target = point.into();
+    }
+
+    pub fn toggle_show_orbit_target(&mut self) {
+        self.show_orbit_target = u32::from(self.show_orbit_target == 0);
+    }
+
     pub fn update_time(&mut self, delta: f32) {
         self.time += delta;
     }
 
+    pub fn wrap_time(&mut self, period: f32) {
+        self.time = self.time.rem_euclid(period);
+    }
+
+    pub fn update_time_b(&mut self, delta: f32) {
+        self.time_b += delta;
+    }
+
+    pub fn wrap_time_b(&mut self, period: f32) {
+        self.time_b = self.time_b.rem_euclid(period);
+    }
+
+    pub fn update_wall_time(&mut self, secs: f32) {
+        self.wall_time = secs;
+    }
+
+    const MIN_NUM_ITERATIONS: u32 = 1;
+    const MAX_NUM_ITERATIONS: u32 = 512;
+
     pub fn update_num_iterations(&mut self, delta: i32) {
-        self.num_iterations = self.num_iterations.saturating_add_signed(delta);
+        self.num_iterations = self
+            .num_iterations
+            .saturating_add_signed(delta)
+            .clamp(Self::MIN_NUM_ITERATIONS, Self::MAX_NUM_ITERATIONS);
     }
 
-    const NUM_SCENES: u32 = 19;
+    pub const NUM_SCENES: u32 = 21;
+    pub const MANDELBOX_SCENE_INDEX: u32 = 20;
 
     pub fn update_scene_index(&mut self, delta: i32) {
         self.scene_index =
             (self.scene_index as i32 + delta).rem_euclid(Self::NUM_SCENES as i32) as u32;
     }
+
+    pub fn scene_index(&self) -> u32 {
+        self.scene_index
+    }
+
+    pub fn set_scene_index(&mut self, index: usize) {
+        self.scene_index = (index % Self::NUM_SCENES as usize) as u32;
+    }
+
+    // leaves the effects and the display settings alone
+    pub fn apply_preset(&mut self, preset: FractalPreset) {
+        let values = preset.values();
+        self.set_scene_index(values.scene_index as usize);
+        self.num_iterations = values.num_iterations;
+        self.fractal_power = values.fractal_power;
+        self.mandelbox_scale = values.mandelbox_scale;
+        self.mandelbox_fold_radius = values.mandelbox_fold_radius;
+    }
+
+    const FRACTAL_POWER_RANGE: (f32, f32) = (2.0, 16.0);
+    const BLOOM_THRESHOLD_RANGE: (f32, f32) = (0.0, 1.0);
+    const STEP_FACTOR_RANGE: (f32, f32) = (0.5, 2.0);
+    const CHROMATIC_ABERRATION_RANGE: (f32, f32) = (0.0, 0.02);
+    const NORMAL_EPSILON_RANGE: (f32, f32) = (1.0e-7, 0.1);
+    const SHARPEN_STRENGTH_RANGE: (f32, f32) = (0.0, 1.0);
+    const FILM_GRAIN_RANGE: (f32, f32) = (0.0, 2.0);
+    const SCANLINE_STRENGTH_RANGE: (f32, f32) = (0.0, 1.0);
+    const SSR_STRENGTH_RANGE: (f32, f32) = (0.0, 1.0);
+    const DEFAULT_SSR_STRENGTH: f32 = 0.5;
+
+    pub fn update_fractal_power(&mut self, delta: f32) {
+        let (min, max) = Self::FRACTAL_POWER_RANGE;
+        self.fractal_power = (self.fractal_power + delta).clamp(min, max);
+    }
+
+    fn lerp_range((min, max): (f32, f32), fraction: f32) -> f32 {
+        min + (max - min) * fraction.clamp(0.0, 1.0)
+    }
+
+    pub fn set_fractal_power_fraction(&mut self, fraction: f32) {
+        self.fractal_power = Self::lerp_range(Self::FRACTAL_POWER_RANGE, fraction);
+    }
+
+    pub fn set_bloom_threshold_fraction(&mut self, fraction: f32) {
+        self.bloom_threshold = Self::lerp_range(Self::BLOOM_THRESHOLD_RANGE, fraction);
+    }
+
+    pub fn set_step_factor_fraction(&mut self, fraction: f32) {
+        self.step_factor = Self::lerp_range(Self::STEP_FACTOR_RANGE, fraction);
+    }
+
+    pub const CANONICAL_MANDELBOX_SCALE: f32 = 2.0;
+    pub const CANONICAL_MANDELBOX_FOLD_RADIUS: f32 = 1.0;
+    const MIN_MANDELBOX_SCALE_MAGNITUDE: f32 = 0.5;
+    const MIN_MANDELBOX_FOLD_RADIUS: f32 = 0.1;
+
+    pub fn update_mandelbox_scale(&mut self, delta: f32) {
+        let scale = self.mandelbox_scale + delta;
+        // scales close to zero are skipped by jumping to the other sign
+        self.mandelbox_scale = if scale.abs() >= Self::MIN_MANDELBOX_SCALE_MAGNITUDE {
+            scale
+        } else {
+            -self.mandelbox_scale.signum() * Self::MIN_MANDELBOX_SCALE_MAGNITUDE
+        };
+    }
+
+    pub fn update_mandelbox_fold_radius(&mut self, delta: f32) {
+        self.mandelbox_fold_radius =
+            (self.mandelbox_fold_radius + delta).max(Self::MIN_MANDELBOX_FOLD_RADIUS);
+    }
+
+    // in linear light, from top to bottom
+    const BACKGROUND_GRADIENTS: [([f32; 3], [f32; 3]); 4] = [
+        ([0.0, 0.0, 0.0], [0.0, 0.0, 0.0]),       // black
+        ([0.0, 0.005, 0.03], [0.01, 0.02, 0.05]), // night sky
+        ([0.1, 0.15, 0.4], [0.9, 0.3, 0.05]),     // sunset
+        ([1.0, 1.0, 1.0], [1.0, 1.0, 1.0]),       // white
+    ];
+
+    pub fn set_background_gradient(&mut self, top: [f32; 3], bottom: [f32; 3]) {
+        self.background_color_top = top;
+        self.background_color_bottom = bottom;
+    }
+
+    const DARK_THEME_GRADIENT: ([f32; 3], [f32; 3]) = ([0.01, 0.01, 0.02], [0.0, 0.0, 0.0]);
+    const LIGHT_THEME_GRADIENT: ([f32; 3], [f32; 3]) = ([0.9, 0.9, 1.0], [0.7, 0.8, 1.0]);
+
+    // follows the OS theme, only called when it changes, so a cycled gradient stays until then
+    pub fn set_theme(&mut self, dark: bool) {
+        let (top, bottom) = if dark {
+            Self::DARK_THEME_GRADIENT
+        } else {
+            Self::LIGHT_THEME_GRADIENT
+        };
+        self.set_background_gradient(top, bottom);
+    }
+
+    pub fn cycle_background_gradient(&mut self) {
+        let current = (self.background_color_top, self.background_color_bottom);
+        let next_index = Self::BACKGROUND_GRADIENTS
+            .iter()
+            .position(|&gradient| gradient == current)
+            .map_or(0, |index| (index + 1) % Self::BACKGROUND_GRADIENTS.len());
+        let (top, bottom) = Self::BACKGROUND_GRADIENTS[next_index];
+        self.set_background_gradient(top, bottom);
+    }
+
+    const NUM_INTERIOR_MODES: u32 = 4;
+
+    pub fn cycle_interior_mode(&mut self) {
+        self.interior_mode = (self.interior_mode + 1) % Self::NUM_INTERIOR_MODES;
+    }
+
+    const DEBUG_MODE_NAMES: [&str; 5] = [
+        "normal",
+        "step count",
+        "normals",
+        "gradient magnitude",
+        "ambient occlusion",
+    ];
+    const NUM_DEBUG_MODES: u32 = Self::DEBUG_MODE_NAMES.len() as u32;
+
+    pub fn cycle_debug_mode(&mut self) {
+        self.debug_mode = (self.debug_mode + 1) % Self::NUM_DEBUG_MODES;
+    }
+
+    // None while rendering normally
+    pub fn debug_mode_name(&self) -> Option<&'static str> {
+        match self.debug_mode {
+            0 => None,
+            mode => Self::DEBUG_MODE_NAMES.get(mode as usize).copied(),
+        }
+    }
+
+    pub fn increment_seed(&mut self) {
+        self.seed = self.seed.wrapping_add(1);
+    }
+
+    pub fn randomize_seed(&mut self) {
+        // the constants of Numerical Recipes
+        self.seed = self.seed.wrapping_mul(1664525).wrapping_add(1013904223);
+    }
+
+    pub fn toggle_dither(&mut self) {
+        self.dither_enabled = u32::from(self.dither_enabled == 0);
+    }
+
+    pub fn is_bloom_enabled(&self) -> bool {
+        self.bloom_enabled != 0
+    }
+
+    pub fn is_ssr_enabled(&self) -> bool {
+        self.ssr_strength != 0.0
+    }
+
+    pub fn toggle_ssr(&mut self) {
+        self.ssr_strength = if self.is_ssr_enabled() {
+            0.0
+        } else {
+            Self::DEFAULT_SSR_STRENGTH
+        };
+    }
+
+    pub fn toggle_bloom(&mut self) {
+        self.bloom_enabled = u32::from(!self.is_bloom_enabled());
+    }
+
+    pub fn update_bloom_threshold(&mut self, delta: f32) {
+        let (min, max) = Self::BLOOM_THRESHOLD_RANGE;
+        self.bloom_threshold = (self.bloom_threshold + delta).clamp(min, max);
+    }
+
+    pub fn update_step_factor(&mut self, delta: f32) {
+        let (min, max) = Self::STEP_FACTOR_RANGE;
+        self.step_factor = (self.step_factor + delta).clamp(min, max);
+    }
+
+    pub fn update_chromatic_aberration(&mut self, delta: f32) {
+        let (min, max) = Self::CHROMATIC_ABERRATION_RANGE;
+        self.chromatic_aberration = (self.chromatic_aberration + delta).clamp(min, max);
+    }
+
+    pub fn cycle_chromatic_aberration(&mut self) {
+        let next = cycle_level(self.chromatic_aberration, &[0.0, 0.005, 0.01]);
+        self.update_chromatic_aberration(next - self.chromatic_aberration);
+    }
+
+    pub fn update_sharpen(&mut self, delta: f32) {
+        let (min, max) = Self::SHARPEN_STRENGTH_RANGE;
+        self.sharpen_strength = (self.sharpen_strength + delta).clamp(min, max);
+    }
+
+    pub fn cycle_sharpen(&mut self) {
+        let next = cycle_level(self.sharpen_strength, &[0.0, 0.3, 0.7]);
+        self.update_sharpen(next - self.sharpen_strength);
+    }
+
+    pub fn update_film_grain(&mut self, delta: f32) {
+        let (min, max) = Self::FILM_GRAIN_RANGE;
+        self.film_grain = (self.film_grain + delta).clamp(min, max);
+    }
+
+    pub fn cycle_film_grain(&mut self) {
+        let next = cycle_level(self.film_grain, &[0.0, 0.5, 1.0, 2.0]);
+        self.update_film_grain(next - self.film_grain);
+    }
+
+    pub fn update_scanline_strength(&mut self, delta: f32) {
+        let (min, max) = Self::SCANLINE_STRENGTH_RANGE;
+        self.scanline_strength = (self.scanline_strength + delta).clamp(min, max);
+    }
+
+    pub fn cycle_scanline_strength(&mut self) {
+        let next = cycle_level(self.scanline_strength, &[0.0, 0.3, 0.7]);
+        self.update_scanline_strength(next - self.scanline_strength);
+    }
+
+    pub fn update_normal_epsilon(&mut self, factor: f32) {
+        let (min, max) = Self::NORMAL_EPSILON_RANGE;
+        self.normal_epsilon = (self.normal_epsilon * factor).clamp(min, max);
+    }
+
+    pub fn viewport_count(&self) -> u32 {
+        self.viewport_count
+    }
+
+    pub fn toggle_split_screen(&mut self) {
+        self.viewport_count = if self.viewport_count == 1 { 2 } else { 1 };
+    }
+
+    pub fn validate(&self) -> Vec<String> {
+        let mut violations = Vec::new();
+        let mut check = |name: &str, value: f32, (min, max): (f32, f32)| {
+            if value.is_nan() {
+                violations.push(format!("{name} is not a number"));
+            } else if value < min {
+                violations.push(format!("{name} out of range: {value} < {min}"));
+            } else if value > max {
+                violations.push(format!("{name} out of range: {value} > {max}"));
+            }
+        };
+        let last_index = |count: u32| (count - 1) as f32;
+        check(
+            "scene_index",
+            self.scene_index as f32,
+            (0.0, last_index(Self::NUM_SCENES)),
+        );
+        check(
+            "num_iterations",
+            self.num_iterations as f32,
+            (
+                Self::MIN_NUM_ITERATIONS as f32,
+                Self::MAX_NUM_ITERATIONS as f32,
+            ),
+        );
+        check(
+            "fractal_power",
+            self.fractal_power,
+            Self::FRACTAL_POWER_RANGE,
+        );
+        check(
+            "bloom_threshold",
+            self.bloom_threshold,
+            Self::BLOOM_THRESHOLD_RANGE,
+        );
+        check("viewport_count", self.viewport_count as f32, (1.0, 2.0));
+        check("blend_views", self.blend_views, (0.0, 1.0));
+        check("step_factor", self.step_factor, Self::STEP_FACTOR_RANGE);
+        check(
+            "chromatic_aberration",
+            self.chromatic_aberration,
+            Self::CHROMATIC_ABERRATION_RANGE,
+        );
+        check(
+            "mandelbox_scale magnitude",
+            self.mandelbox_scale.abs(),
+            (Self::MIN_MANDELBOX_SCALE_MAGNITUDE, f32::INFINITY),
+        );
+        check(
+            "mandelbox_fold_radius",
+            self.mandelbox_fold_radius,
+            (Self::MIN_MANDELBOX_FOLD_RADIUS, f32::INFINITY),
+        );
+        check(
+            "interior_mode",
+            self.interior_mode as f32,
+            (0.0, last_index(Self::NUM_INTERIOR_MODES)),
+        );
+        check(
+            "normal_epsilon",
+            self.normal_epsilon,
+            Self::NORMAL_EPSILON_RANGE,
+        );
+        check(
+            "sharpen_strength",
+            self.sharpen_strength,
+            Self::SHARPEN_STRENGTH_RANGE,
+        );
+        check("film_grain", self.film_grain, Self::FILM_GRAIN_RANGE);
+        check(
+            "scanline_strength",
+            self.scanline_strength,
+            Self::SCANLINE_STRENGTH_RANGE,
+        );
+        check("ssr_strength", self.ssr_strength, Self::SSR_STRENGTH_RANGE);
+        check(
+            "debug_mode",
+            self.debug_mode as f32,
+            (0.0, last_index(Self::NUM_DEBUG_MODES)),
+        );
+        violations
+    }
+
+    // every uniform field in declaration order, shared by the diffs and the WGSL snapshots
+    fn fields(&self) -> [(&'static str, FieldValue<'_>, Tracking); 34] {
+        use FieldValue::{Matrix, Scalar, Unsigned, Vector};
+        use Tracking::{Components, PerFrame, Tracked};
+        [
+            ("camera_matrix", Matrix(&self.camera_matrix), PerFrame),
+            ("aspect_scale", Vector(&self.aspect_scale), PerFrame),
+            ("time", Scalar(self.time), PerFrame),
+            ("num_iterations", Unsigned(self.num_iterations), Tracked),
+            ("scene_index", Unsigned(self.scene_index), Tracked),
+            ("fractal_power", Scalar(self.fractal_power), Tracked),
+            ("dither_enabled", Unsigned(self.dither_enabled), Tracked),
+            ("bloom_enabled", Unsigned(self.bloom_enabled), Tracked),
+            ("bloom_threshold", Scalar(self.bloom_threshold), Tracked),
+            ("bloom_strength", Scalar(self.bloom_strength), Tracked),
+            ("viewport_count", Unsigned(self.viewport_count), Tracked),
+            ("step_factor", Scalar(self.step_factor), Tracked),
+            (
+                "chromatic_aberration",
+                Scalar(self.chromatic_aberration),
+                Tracked,
+            ),
+            ("mandelbox_scale", Scalar(self.mandelbox_scale), Tracked),
+            (
+                "mandelbox_fold_radius",
+                Scalar(self.mandelbox_fold_radius),
+                Tracked,
+            ),
+            (
+                "background_color_top",
+                Vector(&self.background_color_top),
+                Components(&[
+                    "background_color_top.r",
+                    "background_color_top.g",
+                    "background_color_top.b",
+                ]),
+            ),
+            (
+                "background_color_bottom",
+                Vector(&self.background_color_bottom),
+                Components(&[
+                    "background_color_bottom.r",
+                    "background_color_bottom.g",
+                    "background_color_bottom.b",
+                ]),
+            ),
+            ("interior_mode", Unsigned(self.interior_mode), Tracked),
+            ("orbit_target", Vector(&self.orbit_target), PerFrame),
+            (
+                "show_orbit_target",
+                Unsigned(self.show_orbit_target),
+                Tracked,
+            ),
+            ("normal_epsilon", Scalar(self.normal_epsilon), Tracked),
+            ("camera_matrix_b", Matrix(&self.camera_matrix_b), PerFrame),
+            ("blend_views", Scalar(self.blend_views), Tracked),
+            ("jitter", Vector(&self.jitter), PerFrame),
+            ("jitter_enabled", Unsigned(self.jitter_enabled), Tracked),
+            ("wall_time", Scalar(self.wall_time), PerFrame),
+            ("sharpen_strength", Scalar(self.sharpen_strength), Tracked),
+            ("film_grain", Scalar(self.film_grain), Tracked),
+            ("seed", Unsigned(self.seed), Tracked),
+            ("debug_mode", Unsigned(self.debug_mode), Tracked),
+            (
+                "letterbox_scale",
+                Vector(&self.letterbox_scale),
+                Components(&["letterbox_scale.x", "letterbox_scale.y"]),
+            ),
+            ("scanline_strength", Scalar(self.scanline_strength), Tracked),
+            ("ssr_strength", Scalar(self.ssr_strength), Tracked),
+            ("time_b", Scalar(self.time_b), PerFrame),
+        ]
+    }
+
+    // f64 holds every u32 exactly, so even large seeds that differ by one show up
+    fn tracked_values(&self) -> Vec<(&'static str, f64)> {
+        let mut values = Vec::new();
+        for (name, value, tracking) in self.fields() {
+            let names = match tracking {
+                Tracking::PerFrame => continue,
+                Tracking::Tracked => &[name][..],
+                Tracking::Components(names) => names,
+            };
+            values.extend(names.iter().copied().zip(value.components()));
+        }
+        values
+    }
+
+    const DIFF_THRESHOLD: f32 = 1.0e-6;
+
+    pub fn diff(&self, other: &Parameters) -> Vec<(&'static str, f32)> {
+        self.tracked_values()
+            .into_iter()
+            .zip(other.tracked_values())
+            .map(|((name, value), (_, other_value))| (name, (value - other_value) as f32))
+            .filter(|&(_, delta)| delta.abs() > Self::DIFF_THRESHOLD)
+            .collect()
+    }
+
+    const SCENE_NAMES: [&'static str; Self::NUM_SCENES as usize] = [
+        "Menger sponge (cross 1/6, scale 3)",
+        "Menger sponge (cross 1/5, scale 3)",
+        "Menger sponge (cross 1/4, scale 3)",
+        "Menger sponge (cross 1/3, scale 3)",
+        "Menger sponge (animated cross, scale 3)",
+        "Menger sponge (cross 1/6, scale 2)",
+        "Menger sponge (cross 1/4, scale 2)",
+        "Menger sponge (cross 1/8, scale 2)",
+        "Menger sponge (animated cross, scale 2)",
+        "Menger sponge (cross 1/4, scale 4)",
+        "Menger sponge (cross 1/5, scale 5)",
+        "Menger sponge (cross 1/4, scale 6)",
+        "Menger sponge (cross 1/3, animated scale)",
+        "Menger sponge (cross 1/4, animated scale)",
+        "Menger sponge (cross 1/6, animated scale)",
+        "Sierpinski tetrahedron",
+        "Koch surface",
+        "Koch surface (animated)",
+        "Mandelbulb (animated power)",
+        "Mandelbulb",
+        "Mandelbox",
+    ];
+
+    fn scene_name(&self) -> &'static str {
+        Self::SCENE_NAMES[self.scene_index as usize]
+    }
+
+    // pasteable in place of the uniform declaration in fragment.wgsl
+    #[allow(clippy::wrong_self_convention)] // borrowing avoids copying the whole uniform
+    pub fn to_wgsl_const(&self) -> String {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+        let mut wgsl = format!(
+            "// parameters snapshot taken at {timestamp} (seconds since the Unix epoch) in scene {} ({})\n",
+            self.scene_index,
+            self.scene_name()
+        );
+        wgsl.push_str("const parameters = Parameters(\n");
+        for (name, value, _) in self.fields() {
+            wgsl.push_str(&format!("    {}, // {name}\n", value.to_wgsl()));
+        }
+        wgsl.push_str(");\n");
+        wgsl
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum FieldValue<'a> {
+    Scalar(f32),
+    Unsigned(u32),
+    Vector(&'a [f32]),
+    Matrix(&'a [[f32; 4]; 4]),
+}
+
+impl FieldValue<'_> {
+    fn to_wgsl(self) -> String {
+        fn scalar(value: f32) -> String {
+            format!("{value:?}")
+        }
+        fn vector(values: &[f32]) -> String {
+            let components: Vec<_> = values.iter().copied().map(scalar).collect();
+            format!("vec{}({})", values.len(), components.join(", "))
+        }
+        match self {
+            Self::Scalar(value) => scalar(value),
+            Self::Unsigned(value) => format!("{value}u"),
+            Self::Vector(values) => vector(values),
+            Self::Matrix(columns) => {
+                let columns: Vec<_> = columns.iter().map(|column| vector(column)).collect();
+                format!("mat4x4({})", columns.join(", "))
+            }
+        }
+    }
+
+    fn components(self) -> Vec<f64> {
+        match self {
+            Self::Scalar(value) => vec![value.into()],
+            Self::Unsigned(value) => vec![value.into()],
+            Self::Vector(values) => values.iter().copied().map(f64::from).collect(),
+            Self::Matrix(columns) => columns.iter().flatten().copied().map(f64::from).collect(),
+        }
+    }
+}
+
+// how diffs compare a field
+#[derive(Debug, Clone, Copy)]
+enum Tracking {
+    // changes every frame like the camera, so it would always differ
+    PerFrame,
+    Tracked,
+    // compared per component, with these names
+    Components(&'static [&'static str]),
+}
+
+// the first of the ascending levels above the value, wrapping around to the first level
+fn cycle_level(value: f32, levels: &[f32]) -> f32 {
+    levels
+        .iter()
+        .copied()
+        .find(|&level| level > value)
+        .unwrap_or(levels[0])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_num_iterations(num_iterations: u32) -> Parameters {
+        Parameters {
+            num_iterations,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn levels_cycle_upwards_and_wrap_around() {
+        let levels = [0.0, 0.5, 1.0];
+        assert_eq!(cycle_level(0.0, &levels), 0.5);
+        assert_eq!(cycle_level(0.5, &levels), 1.0);
+        assert_eq!(cycle_level(1.0, &levels), 0.0);
+        // values between the levels move to the next one
+        assert_eq!(cycle_level(0.7, &levels), 1.0);
+    }
+
+    #[test]
+    fn default_parameters_are_valid() {
+        assert_eq!(Parameters::default().validate(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn num_iterations_change_by_the_delta() {
+        let mut parameters = with_num_iterations(10);
+        parameters.update_num_iterations(5);
+        assert_eq!(parameters.num_iterations, 15);
+        parameters.update_num_iterations(-3);
+        assert_eq!(parameters.num_iterations, 12);
+    }
+
+    #[test]
+    fn num_iterations_stop_at_one() {
+        let mut parameters = with_num_iterations(1);
+        parameters.update_num_iterations(-1);
+        assert_eq!(parameters.num_iterations, 1);
+        parameters.update_num_iterations(i32::MIN);
+        assert_eq!(parameters.num_iterations, 1);
+    }
+
+    #[test]
+    fn num_iterations_stop_at_512() {
+        let mut parameters = with_num_iterations(510);
+        parameters.update_num_iterations(5);
+        assert_eq!(parameters.num_iterations, 512);
+        parameters.update_num_iterations(i32::MAX);
+        assert_eq!(parameters.num_iterations, 512);
+    }
+
+    #[test]
+    fn num_iterations_out_of_range_are_clamped_on_update() {
+        let mut parameters = with_num_iterations(0);
+        parameters.update_num_iterations(0);
+        assert_eq!(parameters.num_iterations, 1);
+        let mut parameters = with_num_iterations(1000);
+        parameters.update_num_iterations(0);
+        assert_eq!(parameters.num_iterations, 512);
+    }
+
+    #[test]
+    fn zero_num_iterations_are_invalid() {
+        let violations = with_num_iterations(0).validate();
+        assert_eq!(violations, ["num_iterations out of range: 0 < 1"]);
+    }
+
+    #[test]
+    fn scene_index_within_the_scene_count_is_kept() {
+        let mut parameters = Parameters::default();
+        parameters.set_scene_index(7);
+        assert_eq!(parameters.scene_index(), 7);
+        parameters.set_scene_index(Parameters::NUM_SCENES as usize - 1);
+        assert_eq!(parameters.scene_index(), Parameters::NUM_SCENES - 1);
+    }
+
+    #[test]
+    fn scene_index_wraps_around_the_scene_count() {
+        let mut parameters = Parameters::default();
+        parameters.set_scene_index(Parameters::NUM_SCENES as usize);
+        assert_eq!(parameters.scene_index(), 0);
+        parameters.set_scene_index(Parameters::NUM_SCENES as usize * 3 + 2);
+        assert_eq!(parameters.scene_index(), 2);
+    }
+
+    #[test]
+    fn scene_index_beyond_u32_wraps() {
+        let mut parameters = Parameters::default();
+        let index = u32::MAX as usize + 1;
+        parameters.set_scene_index(index);
+        assert_eq!(
+            parameters.scene_index() as usize,
+            index % Parameters::NUM_SCENES as usize
+        );
+    }
+
+    #[test]
+    fn canonical_mandelbox_preset_shows_the_default_mandelbox() {
+        let mut parameters = Parameters {
+            mandelbox_scale: -1.5,
+            mandelbox_fold_radius: 0.5,
+            ..Default::default()
+        };
+        parameters.apply_preset(FractalPreset::MandelboxCanonical);
+        assert_eq!(parameters.scene_index(), Parameters::MANDELBOX_SCENE_INDEX);
+        let defaults = Parameters::default();
+        assert_eq!(parameters.mandelbox_scale, defaults.mandelbox_scale);
+        assert_eq!(
+            parameters.mandelbox_fold_radius,
+            defaults.mandelbox_fold_radius
+        );
+    }
+
+    #[test]
+    fn diff_lists_changed_values_but_not_per_frame_ones() {
+        let previous = Parameters::default();
+        let mut parameters = previous;
+        parameters.update_time(1.0);
+        parameters.background_color_top[1] += 0.5;
+        parameters.seed += 1;
+        assert_eq!(
+            parameters.diff(&previous),
+            [("background_color_top.g", 0.5), ("seed", 1.0)]
+        );
+    }
+
+    #[test]
+    fn wgsl_const_lists_every_field_and_names_the_scene() {
+        let mut parameters = Parameters::default();
+        parameters.set_scene_index(Parameters::MANDELBOX_SCENE_INDEX as usize);
+        let wgsl = parameters.to_wgsl_const();
+        let mut lines = wgsl.lines();
+        assert!(lines.next().unwrap().ends_with("in scene 20 (Mandelbox)"));
+        assert_eq!(lines.next(), Some("const parameters = Parameters("));
+        assert_eq!(lines.count(), parameters.fields().len() + 1);
+        assert!(wgsl.contains("    20u, // scene_index\n"));
+    }
 }
@@ -1,55 +1,253 @@
-use crate::{parameters::Parameters, utils::create_render_pipeline};
-use anyhow::{Context, Ok, Result};
+use crate::{
+    gpu_timer::GpuTimer,
+    parameters::{FastParameters, Parameters},
+    pipeline_statistics::PipelineStatistics,
+    utils::{
+        RenderPipelineSpec, create_render_pipeline, handle_device_errors_async,
+        preferred_surface_format,
+    },
+};
+use anyhow::{Context, Ok, Result, bail};
 use std::{borrow::Cow, sync::Arc};
 use wgpu::{
-    Adapter, AddressMode, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
-    BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType, Buffer,
-    BufferBinding, BufferBindingType, BufferDescriptor, BufferUsages, Device, DeviceDescriptor,
-    FilterMode, Instance, InstanceDescriptor, PowerPreference, Queue, RenderPipeline,
-    RequestAdapterOptions, Sampler, SamplerBindingType, SamplerDescriptor, ShaderModule,
-    ShaderModuleDescriptor, ShaderSource, ShaderStages, Surface, TextureSampleType,
-    TextureViewDimension,
+    Adapter, AdapterInfo, AddressMode, Backends, BindGroup, BindGroupDescriptor, BindGroupEntry,
+    BindGroupLayout, BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType,
+    Buffer, BufferBinding, BufferBindingType, BufferDescriptor, BufferUsages, Device,
+    DeviceDescriptor, ErrorFilter, Extent3d, Features, FilterMode, Instance, InstanceDescriptor,
+    Limits, PowerPreference, PresentMode, Queue, RenderPipeline, RequestAdapterOptions, Sampler,
+    SamplerBindingType, SamplerDescriptor, ShaderModule, ShaderModuleDescriptor, ShaderSource,
+    ShaderStages, Surface, SurfaceCapabilities, SurfaceConfiguration, Texture, TextureDescriptor,
+    TextureDimension, TextureFormat, TextureSampleType, TextureUsages, TextureViewDimension,
 };
 use winit::{dpi::PhysicalSize, event_loop::ActiveEventLoop, window::Window};
 
+#[derive(Debug)]
+pub enum Output {
+    Window {
+        window: Arc<Window>,
+        surface: Surface<'static>,
+        surface_format: TextureFormat,
+    },
+    OffScreen {
+        texture: Texture,
+    },
+}
+
 #[derive(Debug)]
 pub struct PersistentGraphics {
-    pub window: Arc<Window>,
-    pub surface: Surface<'static>,
+    pub output: Output,
     adapter: Adapter,
+    features: Features,
     pub device: Device,
     pub queue: Queue,
     pub render_texture_sampler: Sampler,
     pub blit_bind_group_layout: BindGroupLayout,
     pub blit_render_pipeline: RenderPipeline,
+    pub error_render_pipeline: RenderPipeline,
     pub vertex_shader: ShaderModule,
     parameters_buffer: Buffer,
     pub parameters_bind_group_layout: BindGroupLayout,
     pub parameters_bind_group: BindGroup,
+    pub pipeline_statistics: Option<PipelineStatistics>,
+    pub gpu_timer: Option<GpuTimer>,
     pub is_cursor_grabbed: bool,
+    vsync: bool,
 }
 
 impl PersistentGraphics {
-    pub async fn init(event_loop: &ActiveEventLoop) -> Result<Self> {
+    pub const TITLE: &'static str = "Fractals";
+
+    pub const OFF_SCREEN_FORMAT: TextureFormat = TextureFormat::Rgba8UnormSrgb;
+
+    pub async fn init(
+        event_loop: &ActiveEventLoop,
+        preferred_adapter: Option<&str>,
+        limits: Limits,
+    ) -> Result<Self> {
         let window = Arc::new(
             event_loop
-                .create_window(Window::default_attributes().with_title("Fractals"))
+                .create_window(Window::default_attributes().with_title(Self::TITLE))
                 .context("failed to create window")?,
         );
+        Self::init_with_window(window, preferred_adapter, limits).await
+    }
+
+    pub async fn init_with_window(
+        window: Arc<Window>,
+        preferred_adapter: Option<&str>,
+        limits: Limits,
+    ) -> Result<Self> {
         let instance = Instance::new(&InstanceDescriptor::from_env_or_default());
         let surface = instance
             .create_surface(window.clone())
             .context("failed to create surface")?;
-        let adapter = instance
-            .request_adapter(&RequestAdapterOptions {
-                power_preference: PowerPreference::HighPerformance,
-                compatible_surface: Some(&surface),
-                force_fallback_adapter: false,
+        let adapter = Self::request_adapter(&instance, Some(&surface), preferred_adapter).await?;
+        let surface_format = preferred_surface_format(&surface.get_capabilities(&adapter))?;
+        eprintln!("using surface format {surface_format:?}");
+        Self::init_with_output(adapter, limits, surface_format, |_| Output::Window {
+            window,
+            surface,
+            surface_format,
+        })
+        .await
+    }
+
+    pub async fn init_off_screen(
+        width: u32,
+        height: u32,
+        preferred_adapter: Option<&str>,
+        limits: Limits,
+    ) -> Result<Self> {
+        let instance = Instance::new(&InstanceDescriptor::from_env_or_default());
+        let adapter = Self::request_adapter(&instance, None, preferred_adapter).await?;
+        Self::init_with_output(adapter, limits, Self::OFF_SCREEN_FORMAT, |device| {
+            let texture = device.create_texture(&TextureDescriptor {
+                label: Some("off_screen_texture"),
+                dimension: TextureDimension::D2,
+                size: Extent3d {
+                    width,
+                    height,
+                    ..Default::default()
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                format: Self::OFF_SCREEN_FORMAT,
+                usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+                view_formats: &[],
+            });
+            Output::OffScreen { texture }
+        })
+        .await
+    }
+
+    pub fn enumerate_adapters(instance: &Instance) -> Vec<String> {
+        instance
+            .enumerate_adapters(Backends::all())
+            .iter()
+            .map(|adapter| adapter.get_info().name)
+            .collect()
+    }
+
+    async fn request_adapter(
+        instance: &Instance,
+        surface: Option<&Surface<'_>>,
+        preferred_adapter: Option<&str>,
+    ) -> Result<Adapter> {
+        let adapter = match preferred_adapter
+            .and_then(|name| Self::find_adapter_by_name(instance, surface, name))
+        {
+            Some(adapter) => adapter,
+            None => {
+                if let Some(name) = preferred_adapter {
+                    let available = Self::enumerate_adapters(instance);
+                    eprintln!(
+                        "no adapter matching {name:?} found (available: {available:?}), \
+                        falling back to high performance adapter"
+                    );
+                }
+                instance
+                    .request_adapter(&RequestAdapterOptions {
+                        power_preference: PowerPreference::HighPerformance,
+                        compatible_surface: surface,
+                        force_fallback_adapter: false,
+                    })
+                    .await
+                    .context("failed to request adapter")?
+            }
+        };
+        let info = adapter.get_info();
+        eprintln!(
+            "using adapter {}, driver {} {}",
+            Self::format_adapter_info(&info),
+            info.driver,
+            info.driver_info
+        );
+        Ok(adapter)
+    }
+
+    // for bug reports, e.g. "NVIDIA GeForce RTX 3070 (10de:2484) via vulkan"
+    fn format_adapter_info(info: &AdapterInfo) -> String {
+        let AdapterInfo {
+            name,
+            vendor,
+            device,
+            backend,
+            ..
+        } = info;
+        format!("{name} ({vendor:04x}:{device:04x}) via {backend}")
+    }
+
+    pub fn adapter_info_string(&self) -> String {
+        Self::format_adapter_info(&self.adapter.get_info())
+    }
+
+    // the adapter that would be used, without creating a window or a device
+    pub async fn query_adapter_info_string(preferred_adapter: Option<&str>) -> Result<String> {
+        let instance = Instance::new(&InstanceDescriptor::from_env_or_default());
+        let adapter = Self::request_adapter(&instance, None, preferred_adapter).await?;
+        Ok(Self::format_adapter_info(&adapter.get_info()))
+    }
+
+    fn find_adapter_by_name(
+        instance: &Instance,
+        surface: Option<&Surface<'_>>,
+        name: &str,
+    ) -> Option<Adapter> {
+        let name = name.to_lowercase();
+        instance
+            .enumerate_adapters(Backends::all())
+            .into_iter()
+            .find(|adapter| {
+                adapter.get_info().name.to_lowercase().contains(&name)
+                    && surface.is_none_or(|surface| adapter.is_surface_supported(surface))
             })
-            .await
-            .context("failed to request adapter")?;
+    }
+
+    // for shaders that run into the default limits
+    pub fn relaxed_limits() -> Limits {
+        Limits {
+            max_uniform_buffer_binding_size: 64 << 10,
+            max_compute_workgroup_size_x: 1024,
+            ..Default::default()
+        }
+    }
+
+    // requesting the device would fail without naming the unsupported limits
+    fn check_limits(adapter: &Adapter, limits: &Limits) -> Result<()> {
+        let mut unsupported = Vec::new();
+        limits.check_limits_with_fail_fn(&adapter.limits(), false, |name, requested, allowed| {
+            unsupported.push(format!(
+                "{name} (requested {requested}, supported {allowed})"
+            ));
+        });
+        if !unsupported.is_empty() {
+            bail!(
+                "the adapter does not support the requested limits: {}",
+                unsupported.join(", ")
+            );
+        }
+        Ok(())
+    }
+
+    async fn init_with_output(
+        adapter: Adapter,
+        limits: Limits,
+        output_format: TextureFormat,
+        create_output: impl FnOnce(&Device) -> Output,
+    ) -> Result<Self> {
+        let features = Self::negotiate_features(&adapter);
+        let mut required_limits = limits;
+        if features.contains(Features::PUSH_CONSTANTS) {
+            required_limits.max_push_constant_size = Self::PUSH_CONSTANTS_SIZE;
+        }
+        Self::check_limits(&adapter, &required_limits)?;
+        let device_descriptor = DeviceDescriptor {
+            required_features: features,
+            required_limits,
+            ..Default::default()
+        };
         let (device, queue) = adapter
-            .request_device(&DeviceDescriptor::default())
+            .request_device(&device_descriptor)
             .await
             .context("failed to request device")?;
         let render_texture_sampler = device.create_sampler(&SamplerDescriptor {
@@ -62,14 +260,24 @@ impl PersistentGraphics {
             mipmap_filter: FilterMode::Nearest,
             ..Default::default()
         });
-        let vertex_shader = device.create_shader_module(ShaderModuleDescriptor {
-            label: Some("vertex_shader"),
-            source: ShaderSource::Wgsl(Cow::Borrowed(include_str!("./vertex.wgsl"))),
-        });
-        let blit_fragment_shader = device.create_shader_module(ShaderModuleDescriptor {
-            label: Some("blit_fragment_shader"),
-            source: ShaderSource::Wgsl(Cow::Borrowed(include_str!("./blit.wgsl"))),
-        });
+        let (vertex_shader, blit_fragment_shader, error_fragment_shader) =
+            handle_device_errors_async(&device, ErrorFilter::Validation, || {
+                let vertex_shader = device.create_shader_module(ShaderModuleDescriptor {
+                    label: Some("vertex_shader"),
+                    source: ShaderSource::Wgsl(Cow::Borrowed(include_str!("./vertex.wgsl"))),
+                });
+                let blit_fragment_shader = device.create_shader_module(ShaderModuleDescriptor {
+                    label: Some("blit_fragment_shader"),
+                    source: ShaderSource::Wgsl(Cow::Borrowed(include_str!("./blit.wgsl"))),
+                });
+                let error_fragment_shader = device.create_shader_module(ShaderModuleDescriptor {
+                    label: Some("error_fragment_shader"),
+                    source: ShaderSource::Wgsl(Cow::Borrowed(include_str!("./error.wgsl"))),
+                });
+                (vertex_shader, blit_fragment_shader, error_fragment_shader)
+            })
+            .await
+            .context("failed to validate the built-in shaders")?;
         let blit_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
             label: Some("blit_bind_group_layout"),
             entries: &[
@@ -91,17 +299,6 @@ impl PersistentGraphics {
                 },
             ],
         });
-        let surface_capabilities = surface.get_capabilities(&adapter);
-        let surface_format = surface_capabilities.formats[0];
-        let blit_render_pipeline = create_render_pipeline(
-            &device,
-            "blit_render_pipeline_layout",
-            &blit_bind_group_layout,
-            "blit_render_pipeline",
-            &vertex_shader,
-            &blit_fragment_shader,
-            surface_format,
-        );
         let parameters_buffer = device.create_buffer(&BufferDescriptor {
             label: Some("parameters_buffer"),
             mapped_at_creation: false,
@@ -136,34 +333,226 @@ impl PersistentGraphics {
                 }),
             }],
         });
-        Ok(Self {
-            window,
-            surface,
+        let blit_render_pipeline = create_render_pipeline(
+            &device,
+            &RenderPipelineSpec {
+                layout_label: "blit_render_pipeline_layout",
+                // the render texture, the parameters, the bloom and the reflections
+                bind_group_layouts: &[
+                    &blit_bind_group_layout,
+                    &parameters_bind_group_layout,
+                    &blit_bind_group_layout,
+                    &blit_bind_group_layout,
+                ],
+                push_constant_ranges: &[],
+                label: "blit_render_pipeline",
+                vertex_shader: &vertex_shader,
+                fragment_shader: &blit_fragment_shader,
+                fragment_entry_point: "fragment_main",
+                texture_formats: &[output_format],
+            },
+        );
+        let error_render_pipeline = create_render_pipeline(
+            &device,
+            &RenderPipelineSpec {
+                layout_label: "error_render_pipeline_layout",
+                bind_group_layouts: &[],
+                push_constant_ranges: &[],
+                label: "error_render_pipeline",
+                vertex_shader: &vertex_shader,
+                fragment_shader: &error_fragment_shader,
+                fragment_entry_point: "fragment_main",
+                texture_formats: &[output_format],
+            },
+        );
+        let pipeline_statistics = features
+            .contains(Features::PIPELINE_STATISTICS_QUERY)
+            .then(|| PipelineStatistics::init(&device));
+        let gpu_timer = features
+            .contains(Features::TIMESTAMP_QUERY)
+            .then(|| GpuTimer::init(&device, &queue));
+        let output = create_output(&device);
+        let persistent = Self {
+            output,
             adapter,
+            features,
             device,
             queue,
             render_texture_sampler,
             blit_bind_group_layout,
             blit_render_pipeline,
+            error_render_pipeline,
             vertex_shader,
             parameters_buffer,
             parameters_bind_group_layout,
             parameters_bind_group,
+            pipeline_statistics,
+            gpu_timer,
             is_cursor_grabbed: false,
-        })
+            vsync: true,
+        };
+        if cfg!(debug_assertions) {
+            let path = if persistent.supports_push_constants() {
+                "push constants"
+            } else {
+                "the parameters uniform buffer"
+            };
+            eprintln!("sending per-frame parameters via {path}");
+        }
+        Ok(persistent)
+    }
+
+    pub const PUSH_CONSTANTS_SIZE: u32 = size_of::<FastParameters>() as u32;
+
+    // the features the app can make use of, none of them are necessary
+    fn required_features() -> Features {
+        Features::PUSH_CONSTANTS | Features::PIPELINE_STATISTICS_QUERY | Features::TIMESTAMP_QUERY
+    }
+
+    fn negotiate_features(adapter: &Adapter) -> Features {
+        let required = Self::required_features();
+        let mut granted = adapter.features() & required;
+        if adapter.limits().max_push_constant_size < Self::PUSH_CONSTANTS_SIZE {
+            granted.remove(Features::PUSH_CONSTANTS);
+        }
+        let missing = required - granted;
+        if !missing.is_empty() {
+            eprintln!("the adapter does not support these optional features: {missing:?}");
+        }
+        granted
+    }
+
+    pub fn has_feature(&self, feature: Features) -> bool {
+        self.features.contains(feature)
+    }
+
+    pub fn supports_push_constants(&self) -> bool {
+        self.has_feature(Features::PUSH_CONSTANTS)
+    }
+
+    pub fn window(&self) -> Option<&Window> {
+        match &self.output {
+            Output::Window { window, .. } => Some(window),
+            Output::OffScreen { .. } => None,
+        }
+    }
+
+    // a handle that keeps the window open after the graphics are dropped
+    pub fn shared_window(&self) -> Option<Arc<Window>> {
+        match &self.output {
+            Output::Window { window, .. } => Some(window.clone()),
+            Output::OffScreen { .. } => None,
+        }
+    }
+
+    pub fn surface_size(&self) -> (u32, u32) {
+        match &self.output {
+            Output::Window { window, .. } => {
+                let PhysicalSize { width, height } = window.inner_size();
+                (width, height)
+            }
+            Output::OffScreen { texture } => (texture.width(), texture.height()),
+        }
     }
 
     pub fn resize(&self, parameters: &mut Parameters) -> Result<()> {
-        let PhysicalSize { width, height } = self.window.inner_size();
-        let config = self
-            .surface
-            .get_default_config(&self.adapter, width, height)
-            .context("failed to get surface config")?;
-        self.surface.configure(&self.device, &config);
+        self.configure_surface()?;
+        let (width, height) = self.surface_size();
         parameters.update_aspect(width, height);
         Ok(())
     }
 
+    pub fn configure_surface(&self) -> Result<()> {
+        let Output::Window {
+            window,
+            surface,
+            surface_format,
+        } = &self.output
+        else {
+            return Ok(());
+        };
+        let PhysicalSize { width, height } = window.inner_size();
+        let config = match surface.get_default_config(&self.adapter, width, height) {
+            Some(mut config) => {
+                config.format = *surface_format;
+                config.present_mode = self.present_mode();
+                config
+            }
+            None => {
+                eprintln!(
+                    "warning: the surface has no default config for the adapter, \
+                    configuring it from its capabilities instead"
+                );
+                let capabilities = surface.get_capabilities(&self.adapter);
+                Self::fallback_surface_config(&capabilities, *surface_format, width, height)?
+            }
+        };
+        surface.configure(&self.device, &config);
+        Ok(())
+    }
+
+    // the automatic modes fall back to Fifo when the preferred one is unsupported
+    pub fn present_mode(&self) -> PresentMode {
+        if self.vsync {
+            PresentMode::AutoVsync
+        } else {
+            PresentMode::AutoNoVsync
+        }
+    }
+
+    pub fn is_vsync(&self) -> bool {
+        self.vsync
+    }
+
+    // leaves V-Sync on if the surface cannot present without it
+    pub fn set_vsync(&mut self, enabled: bool) -> Result<()> {
+        if let Output::Window { surface, .. } = &self.output
+            && !enabled
+        {
+            let present_modes = surface.get_capabilities(&self.adapter).present_modes;
+            if !present_modes
+                .iter()
+                .any(|mode| matches!(mode, PresentMode::Immediate | PresentMode::Mailbox))
+            {
+                bail!("the surface only supports {present_modes:?}, so V-Sync stays on");
+            }
+        }
+        self.vsync = enabled;
+        self.configure_surface()
+            .context("failed to reconfigure the surface for the new present mode")
+    }
+
+    // the blit and error pipelines target the preferred format, so no other one can be used
+    fn fallback_surface_config(
+        capabilities: &SurfaceCapabilities,
+        preferred_format: TextureFormat,
+        width: u32,
+        height: u32,
+    ) -> Result<SurfaceConfiguration> {
+        if !capabilities.formats.contains(&preferred_format) {
+            bail!(
+                "the surface no longer supports the format {preferred_format:?} the pipelines \
+                were built for (supported: {:?}), restart to pick a supported one",
+                capabilities.formats
+            );
+        }
+        let alpha_mode = *capabilities
+            .alpha_modes
+            .first()
+            .context("the surface supports no alpha modes")?;
+        Ok(SurfaceConfiguration {
+            usage: TextureUsages::RENDER_ATTACHMENT,
+            format: preferred_format,
+            width,
+            height,
+            // the only present mode that is always supported
+            present_mode: PresentMode::Fifo,
+            desired_maximum_frame_latency: 2,
+            alpha_mode,
+            view_formats: Vec::new(),
+        })
+    }
+
     pub fn update_parameters_buffer(&self, parameters: &Parameters) {
         self.queue.write_buffer(
             &self.parameters_buffer,
@@ -173,18 +562,82 @@ impl PersistentGraphics {
     }
 
     pub fn grab_cursor(&mut self) {
+        let Some(window) = self.window() else {
+            return;
+        };
         if self.is_cursor_grabbed {
             return;
         }
-        self.window.set_cursor_visible(false);
+        window.set_cursor_visible(false);
         self.is_cursor_grabbed = true;
     }
 
     pub fn ungrab_cursor(&mut self) {
+        let Some(window) = self.window() else {
+            return;
+        };
         if !self.is_cursor_grabbed {
             return;
         }
-        self.window.set_cursor_visible(true);
+        window.set_cursor_visible(true);
         self.is_cursor_grabbed = false;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wgpu::CompositeAlphaMode;
+
+    fn capabilities(formats: &[TextureFormat]) -> SurfaceCapabilities {
+        SurfaceCapabilities {
+            formats: formats.to_vec(),
+            present_modes: vec![PresentMode::Fifo],
+            alpha_modes: vec![CompositeAlphaMode::Opaque],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn fallback_config_keeps_the_supported_preferred_format() {
+        let capabilities = capabilities(&[TextureFormat::Rgba8Unorm, TextureFormat::Bgra8Unorm]);
+        let config = PersistentGraphics::fallback_surface_config(
+            &capabilities,
+            TextureFormat::Bgra8Unorm,
+            640,
+            480,
+        )
+        .unwrap();
+        assert_eq!(config.format, TextureFormat::Bgra8Unorm);
+        assert_eq!((config.width, config.height), (640, 480));
+        assert_eq!(config.present_mode, PresentMode::Fifo);
+        assert_eq!(config.alpha_mode, CompositeAlphaMode::Opaque);
+    }
+
+    #[test]
+    fn fallback_config_fails_without_the_preferred_format() {
+        let capabilities = capabilities(&[TextureFormat::Rgba8Unorm]);
+        let result = PersistentGraphics::fallback_surface_config(
+            &capabilities,
+            TextureFormat::Bgra8Unorm,
+            640,
+            480,
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn fallback_config_fails_without_alpha_modes() {
+        let capabilities = SurfaceCapabilities {
+            alpha_modes: Vec::new(),
+            ..capabilities(&[TextureFormat::Bgra8Unorm])
+        };
+        let result = PersistentGraphics::fallback_surface_config(
+            &capabilities,
+            TextureFormat::Bgra8Unorm,
+            640,
+            480,
+        );
+        assert!(result.is_err());
+    }
+}
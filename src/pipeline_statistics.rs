@@ -0,0 +1,47 @@
+use crate::query_readback::QueryReadback;
+use std::cell::Cell;
+use wgpu::{CommandEncoder, Device, PipelineStatisticsTypes, QueryType, RenderPass};
+
+// counts the fragment shader invocations of the ray marching pass,
+// the counts are read back a few frames late to not stall the rendering
+#[derive(Debug)]
+pub struct PipelineStatistics {
+    readback: QueryReadback,
+    fragment_shader_invocations: Cell<Option<u64>>,
+}
+
+impl PipelineStatistics {
+    pub fn init(device: &Device) -> Self {
+        let ty =
+            QueryType::PipelineStatistics(PipelineStatisticsTypes::FRAGMENT_SHADER_INVOCATIONS);
+        Self {
+            readback: QueryReadback::init(device, "pipeline_statistics", ty, 1),
+            fragment_shader_invocations: Cell::new(None),
+        }
+    }
+
+    pub fn begin(&self, render_pass: &mut RenderPass) {
+        render_pass.begin_pipeline_statistics_query(self.readback.query_set(), 0);
+    }
+
+    pub fn end(&self, render_pass: &mut RenderPass) {
+        render_pass.end_pipeline_statistics_query();
+    }
+
+    // returns whether the query got resolved, which is skipped while the last readback is pending
+    pub fn resolve(&self, encoder: &mut CommandEncoder) -> bool {
+        if let Some(invocations) = self.readback.finish_readback::<u64>() {
+            self.fragment_shader_invocations.set(Some(invocations));
+        }
+        self.readback.resolve(encoder)
+    }
+
+    // to be called after submitting a resolved query
+    pub fn start_readback(&self) {
+        self.readback.start_readback();
+    }
+
+    pub fn fragment_shader_invocations(&self) -> Option<u64> {
+        self.fragment_shader_invocations.get()
+    }
+}
@@ -0,0 +1,121 @@
+use anyhow::{Context, Result, bail};
+use std::{
+    collections::HashSet,
+    fs::read_to_string,
+    path::{Path, PathBuf},
+};
+
+const INCLUDE_DIRECTIVE: &str = "// #include ";
+
+pub fn preprocess(path: &Path) -> Result<String> {
+    let mut output = String::new();
+    preprocess_into(path, &mut HashSet::new(), &mut output)?;
+    Ok(output)
+}
+
+fn preprocess_into(
+    path: &Path,
+    include_stack: &mut HashSet<PathBuf>,
+    output: &mut String,
+) -> Result<()> {
+    let display_path = path.display();
+    let canonical_path = path
+        .canonicalize()
+        .with_context(|| format!("failed to resolve shader source path {display_path}"))?;
+    if !include_stack.insert(canonical_path.clone()) {
+        bail!("circular include of shader source {display_path}");
+    }
+    let source = read_to_string(path)
+        .with_context(|| format!("failed to read shader source at {display_path}"))?;
+    let directory = path.parent().unwrap_or(Path::new(""));
+    for (index, line) in source.lines().enumerate() {
+        let Some(argument) = line.trim().strip_prefix(INCLUDE_DIRECTIVE) else {
+            output.push_str(line);
+            output.push('\n');
+            continue;
+        };
+        let line_number = index + 1;
+        let included_path = argument
+            .trim()
+            .strip_prefix('"')
+            .and_then(|argument| argument.strip_suffix('"'))
+            .with_context(|| {
+                format!("malformed include directive at {display_path}:{line_number}")
+            })?;
+        preprocess_into(&directory.join(included_path), include_stack, output)
+            .with_context(|| format!("failed to include from {display_path}:{line_number}"))?;
+    }
+    include_stack.remove(&canonical_path);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        env::temp_dir,
+        fs::{create_dir_all, remove_dir_all, write},
+        process,
+    };
+
+    // a fresh directory per test, so tests running in parallel do not share files
+    fn write_sources(test_name: &str, sources: &[(&str, &str)]) -> PathBuf {
+        let directory = temp_dir().join(format!("preprocessor_{test_name}_{}", process::id()));
+        let _ = remove_dir_all(&directory);
+        for (name, source) in sources {
+            let path = directory.join(name);
+            create_dir_all(path.parent().unwrap()).unwrap();
+            write(path, source).unwrap();
+        }
+        directory
+    }
+
+    #[test]
+    fn nested_includes_are_expanded_relative_to_the_including_file() {
+        let directory = write_sources(
+            "nested",
+            &[
+                ("main.wgsl", "a\n// #include \"lib/outer.wgsl\"\nd\n"),
+                ("lib/outer.wgsl", "b\n    // #include \"inner.wgsl\"\n"),
+                ("lib/inner.wgsl", "c\n"),
+            ],
+        );
+        let output = preprocess(&directory.join("main.wgsl"));
+        remove_dir_all(&directory).unwrap();
+        assert_eq!(output.unwrap(), "a\nb\nc\nd\n");
+    }
+
+    #[test]
+    fn including_a_file_twice_in_sequence_is_not_circular() {
+        let directory = write_sources(
+            "repeated",
+            &[
+                (
+                    "main.wgsl",
+                    "// #include \"lib.wgsl\"\n// #include \"lib.wgsl\"\n",
+                ),
+                ("lib.wgsl", "a\n"),
+            ],
+        );
+        let output = preprocess(&directory.join("main.wgsl"));
+        remove_dir_all(&directory).unwrap();
+        assert_eq!(output.unwrap(), "a\na\n");
+    }
+
+    #[test]
+    fn circular_includes_fail() {
+        let directory = write_sources(
+            "circular",
+            &[
+                ("a.wgsl", "// #include \"b.wgsl\"\n"),
+                ("b.wgsl", "// #include \"a.wgsl\"\n"),
+            ],
+        );
+        let error = preprocess(&directory.join("a.wgsl")).unwrap_err();
+        remove_dir_all(&directory).unwrap();
+        assert!(
+            format!("{error:#}").contains("circular include"),
+            "{error:#}"
+        );
+    }
+}
@@ -0,0 +1,94 @@
+use crate::parameters::Parameters;
+use cgmath::Vector3;
+
+// a scene with the parameters and the starting position it looks best with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FractalPreset {
+    MengerSponge,
+    SierpinskiTetrahedron,
+    MandelbulbClassic,
+    MandelbulbQuartic,
+    MandelboxCanonical,
+    MandelboxChasm,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PresetValues {
+    pub scene_index: u32,
+    pub num_iterations: u32,
+    pub fractal_power: f32,
+    pub mandelbox_scale: f32,
+    pub mandelbox_fold_radius: f32,
+    pub camera_position: Vector3<f32>,
+}
+
+impl FractalPreset {
+    const ALL: [Self; 6] = [
+        Self::MengerSponge,
+        Self::SierpinskiTetrahedron,
+        Self::MandelbulbClassic,
+        Self::MandelbulbQuartic,
+        Self::MandelboxCanonical,
+        Self::MandelboxChasm,
+    ];
+
+    pub fn first() -> Self {
+        Self::ALL[0]
+    }
+
+    pub fn next(self) -> Self {
+        let index = Self::ALL
+            .iter()
+            .position(|&preset| preset == self)
+            .unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    pub fn values(self) -> PresetValues {
+        // the parameters of the other scenes, which do not affect this one
+        let defaults = PresetValues {
+            scene_index: 0,
+            num_iterations: 4,
+            fractal_power: 8.0,
+            mandelbox_scale: Parameters::CANONICAL_MANDELBOX_SCALE,
+            mandelbox_fold_radius: Parameters::CANONICAL_MANDELBOX_FOLD_RADIUS,
+            camera_position: Vector3::new(0.8, 0.6, -1.4),
+        };
+        match self {
+            Self::MengerSponge => defaults,
+            Self::SierpinskiTetrahedron => PresetValues {
+                scene_index: 15,
+                num_iterations: 6,
+                camera_position: Vector3::new(0.3, 0.2, -1.5),
+                ..defaults
+            },
+            Self::MandelbulbClassic => PresetValues {
+                scene_index: 19,
+                num_iterations: 8,
+                camera_position: Vector3::new(0.0, 0.6, -2.0),
+                ..defaults
+            },
+            Self::MandelbulbQuartic => PresetValues {
+                scene_index: 19,
+                num_iterations: 8,
+                fractal_power: 4.0,
+                camera_position: Vector3::new(1.2, 0.4, -1.5),
+                ..defaults
+            },
+            Self::MandelboxCanonical => PresetValues {
+                scene_index: Parameters::MANDELBOX_SCENE_INDEX,
+                num_iterations: 12,
+                camera_position: Vector3::new(1.0, 0.8, -1.8),
+                ..defaults
+            },
+            // negative scales fold the box inwards into narrow gaps
+            Self::MandelboxChasm => PresetValues {
+                scene_index: Parameters::MANDELBOX_SCENE_INDEX,
+                num_iterations: 12,
+                mandelbox_scale: -1.5,
+                camera_position: Vector3::new(0.2, 0.1, -0.9),
+                ..defaults
+            },
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use bytemuck::{Pod, pod_read_unaligned};
+use std::{
+    cell::RefCell,
+    sync::{Arc, OnceLock},
+};
+use wgpu::{
+    Buffer, BufferAddress, BufferDescriptor, BufferUsages, CommandEncoder, Device, MapMode,
+    PollType, QUERY_SIZE, QuerySet, QuerySetDescriptor, QueryType,
+};
+
+// resolves a query set into a buffer and maps it without stalling the rendering,
+// so the results arrive a few frames late
+#[derive(Debug)]
+pub struct QueryReadback {
+    label: &'static str,
+    device: Device,
+    query_set: QuerySet,
+    count: u32,
+    resolve_buffer: Buffer,
+    readback_buffer: Buffer,
+    // set by the map callback to whether the readback buffer got mapped
+    pending_readback: RefCell<Option<Arc<OnceLock<bool>>>>,
+}
+
+impl QueryReadback {
+    pub fn init(device: &Device, label: &'static str, ty: QueryType, count: u32) -> Self {
+        let size = Self::size(count);
+        let query_set = device.create_query_set(&QuerySetDescriptor {
+            label: Some(&format!("{label}_query_set")),
+            ty,
+            count,
+        });
+        let resolve_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some(&format!("{label}_resolve_buffer")),
+            size,
+            usage: BufferUsages::QUERY_RESOLVE | BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some(&format!("{label}_readback_buffer")),
+            size,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        Self {
+            label,
+            device: device.clone(),
+            query_set,
+            count,
+            resolve_buffer,
+            readback_buffer,
+            pending_readback: RefCell::new(None),
+        }
+    }
+
+    fn size(count: u32) -> BufferAddress {
+        count as BufferAddress * QUERY_SIZE as BufferAddress
+    }
+
+    pub fn query_set(&self) -> &QuerySet {
+        &self.query_set
+    }
+
+    // returns whether the queries got resolved, which is skipped while the last readback is pending
+    pub fn resolve(&self, encoder: &mut CommandEncoder) -> bool {
+        if self.pending_readback.borrow().is_some() {
+            return false;
+        }
+        encoder.resolve_query_set(&self.query_set, 0..self.count, &self.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(
+            &self.resolve_buffer,
+            0,
+            &self.readback_buffer,
+            0,
+            Self::size(self.count),
+        );
+        true
+    }
+
+    // to be called after submitting a resolved query
+    pub fn start_readback(&self) {
+        let mapped = Arc::new(OnceLock::new());
+        let callback_mapped = mapped.clone();
+        self.readback_buffer
+            .slice(..)
+            .map_async(MapMode::Read, move |result| {
+                let _ = callback_mapped.set(result.is_ok());
+            });
+        *self.pending_readback.borrow_mut() = Some(mapped);
+    }
+
+    // returns the results once the pending readback is mapped, T has to span all queries
+    pub fn finish_readback<T: Pod>(&self) -> Option<T> {
+        let mapped = self.pending_readback.borrow().clone()?;
+        // invokes the map callback once the GPU is done, without waiting for it
+        if let Err(error) = self.device.poll(PollType::Poll) {
+            eprintln!("failed to poll for {}: {error}", self.label);
+        }
+        let &mapped = mapped.get()?;
+        *self.pending_readback.borrow_mut() = None;
+        if !mapped {
+            return None;
+        }
+        let results = pod_read_unaligned(&self.readback_buffer.slice(..).get_mapped_range());
+        self.readback_buffer.unmap();
+        Some(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pollster::block_on;
+    use wgpu::{
+        CommandEncoderDescriptor, DeviceDescriptor, Instance, Queue, RequestAdapterOptions,
+    };
+
+    // tests that need a GPU pass trivially on machines without an adapter
+    fn test_device() -> Option<(Device, Queue)> {
+        let instance = Instance::default();
+        let adapter = block_on(instance.request_adapter(&RequestAdapterOptions::default())).ok()?;
+        block_on(adapter.request_device(&DeviceDescriptor::default())).ok()
+    }
+
+    // occlusion queries need no optional features
+    fn occlusion_readback(device: &Device) -> QueryReadback {
+        QueryReadback::init(device, "test", QueryType::Occlusion, 2)
+    }
+
+    fn submit_resolve(device: &Device, queue: &Queue, readback: &QueryReadback) -> bool {
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor::default());
+        let resolved = readback.resolve(&mut encoder);
+        queue.submit(Some(encoder.finish()));
+        resolved
+    }
+
+    #[test]
+    fn nothing_is_read_back_before_a_readback_started() {
+        let Some((device, _queue)) = test_device() else {
+            return;
+        };
+        let readback = occlusion_readback(&device);
+        assert_eq!(readback.finish_readback::<[u64; 2]>(), None);
+    }
+
+    #[test]
+    fn pending_readback_skips_resolving() {
+        let Some((device, queue)) = test_device() else {
+            return;
+        };
+        let readback = occlusion_readback(&device);
+        assert!(submit_resolve(&device, &queue, &readback));
+        readback.start_readback();
+        assert!(!submit_resolve(&device, &queue, &readback));
+    }
+
+    #[test]
+    fn finished_readback_returns_every_query_once() {
+        let Some((device, queue)) = test_device() else {
+            return;
+        };
+        let readback = occlusion_readback(&device);
+        assert!(submit_resolve(&device, &queue, &readback));
+        readback.start_readback();
+        device.poll(PollType::wait_indefinitely()).unwrap();
+        // queries without any pass writing them resolve to zero
+        assert_eq!(readback.finish_readback::<[u64; 2]>(), Some([0, 0]));
+        assert_eq!(readback.finish_readback::<[u64; 2]>(), None);
+        assert!(submit_resolve(&device, &queue, &readback));
+    }
+}
@@ -1,53 +1,279 @@
 use crate::{
-    blit_graphics::BlitGraphics,
     persistent_graphics::PersistentGraphics,
-    utils::{create_render_pipeline, handle_device_errors},
+    preprocessor::preprocess,
+    ssr_graphics::SsrGraphics,
+    utils::{RenderPipelineSpec, create_render_pipeline, handle_device_errors},
 };
 use anyhow::{Context, Result};
-use std::{borrow::Cow, fs::read_to_string, path::Path};
-use wgpu::{ErrorFilter, RenderPipeline, ShaderModuleDescriptor, ShaderSource};
+use std::{
+    borrow::Cow,
+    hash::{DefaultHasher, Hash, Hasher},
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver},
+    thread,
+    time::Instant,
+};
+use wgpu::{
+    BindGroupLayout, Device, ErrorFilter, PushConstantRange, RenderPipeline, ShaderModule,
+    ShaderModuleDescriptor, ShaderSource, ShaderStages, TextureFormat,
+};
 
 #[derive(Debug)]
 pub struct ReloadableGraphics {
     pub render_pipeline: RenderPipeline,
+    compilation_meta: ShaderCompilationMeta,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ShaderCompilationMeta {
+    // of the preprocessed fragment shader source, to tell which version is running
+    pub source_hash: u64,
+    pub compile_duration_ms: u64,
+    pub compiled_at: Instant,
+}
+
+impl ShaderCompilationMeta {
+    fn new(source: &str, compile_start: Instant) -> Self {
+        let mut hasher = DefaultHasher::new();
+        source.hash(&mut hasher);
+        let compiled_at = Instant::now();
+        Self {
+            source_hash: hasher.finish(),
+            compile_duration_ms: (compiled_at - compile_start).as_millis() as u64,
+            compiled_at,
+        }
+    }
+
+    // the first 8 of the 16 hex digits, enough to compare versions at a glance
+    pub fn short_hash(&self) -> String {
+        format!("{:08x}", self.source_hash >> 32)
+    }
+}
+
+// everything the render pipeline is built from, cloned to compile it on another thread
+#[derive(Debug, Clone)]
+pub struct ReloadableInputs {
+    device: Device,
+    vertex_shader: ShaderModule,
+    parameters_bind_group_layout: BindGroupLayout,
+    push_constants: bool,
+    format: TextureFormat,
+}
+
+impl ReloadableInputs {
+    pub fn new(persistent: &PersistentGraphics, format: TextureFormat) -> Self {
+        Self {
+            device: persistent.device.clone(),
+            vertex_shader: persistent.vertex_shader.clone(),
+            parameters_bind_group_layout: persistent.parameters_bind_group_layout.clone(),
+            push_constants: persistent.supports_push_constants(),
+            format,
+        }
+    }
 }
 
 impl ReloadableGraphics {
-    pub fn init(persistent: &PersistentGraphics) -> Result<Self> {
-        let PersistentGraphics {
+    // only debug builds read the shader from here, release builds embed it
+    pub fn fragment_shader_path() -> PathBuf {
+        Path::new(file!()).parent().unwrap().join("fragment.wgsl")
+    }
+
+    pub fn init(persistent: &PersistentGraphics, format: TextureFormat) -> Result<Self> {
+        Self::compile(&ReloadableInputs::new(persistent, format))
+    }
+
+    // keeps rendering smooth while the shader compiles, the result arrives on the receiver,
+    // validation errors of the main thread during compilation are reported as shader errors,
+    // since error scopes belong to the whole device
+    pub fn compile_in_background(inputs: ReloadableInputs) -> Receiver<Result<Self>> {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            // the receiver is gone if another reload was started in the meantime
+            let _ = sender.send(Self::compile(&inputs));
+        });
+        receiver
+    }
+
+    pub fn compilation_meta(&self) -> &ShaderCompilationMeta {
+        &self.compilation_meta
+    }
+
+    fn compile(inputs: &ReloadableInputs) -> Result<Self> {
+        // release builds embed the source that build.rs preprocessed at compile time
+        let fragment_shader_source = if cfg!(debug_assertions) {
+            preprocess(&Self::fragment_shader_path())?
+        } else {
+            include_str!(concat!(env!("OUT_DIR"), "/fragment.wgsl")).to_owned()
+        };
+        Self::compile_source(inputs, fragment_shader_source)
+    }
+
+    // logs the hash and duration whether the compilation succeeds or not
+    fn compile_source(
+        inputs: &ReloadableInputs,
+        mut fragment_shader_source: String,
+    ) -> Result<Self> {
+        let ReloadableInputs {
             device,
             vertex_shader,
             parameters_bind_group_layout,
-            ..
-        } = persistent;
-        let fragment_shader_source = if cfg!(debug_assertions) {
-            let fragment_shader_source_path =
-                Path::new(file!()).parent().unwrap().join("fragment.wgsl");
-            Cow::Owned(
-                read_to_string(&fragment_shader_source_path).with_context(|| {
-                    let path = fragment_shader_source_path.display();
-                    format!("failed to read fragment shader source at {path}")
-                })?,
-            )
+            push_constants,
+            format,
+        } = inputs;
+        let compile_start = Instant::now();
+        // hashed before the fast parameters are appended, which only depend on the device
+        let source_for_hash = fragment_shader_source.clone();
+        fragment_shader_source.push_str(if *push_constants {
+            include_str!("./fast_parameters_push_constant.wgsl")
+        } else {
+            include_str!("./fast_parameters_uniform.wgsl")
+        });
+        let push_constant_ranges = if *push_constants {
+            vec![PushConstantRange {
+                stages: ShaderStages::FRAGMENT,
+                range: 0..PersistentGraphics::PUSH_CONSTANTS_SIZE,
+            }]
         } else {
-            Cow::Borrowed(include_str!("./fragment.wgsl"))
+            Vec::new()
         };
-        let fragment_shader = handle_device_errors(device, ErrorFilter::Validation, || {
-            device.create_shader_module(ShaderModuleDescriptor {
+        // the pipeline creation checks the entry point, the outputs and the bindings,
+        // which a valid shader module can still get wrong
+        let render_pipeline = handle_device_errors(device, ErrorFilter::Validation, || {
+            let fragment_shader = device.create_shader_module(ShaderModuleDescriptor {
                 label: Some("fragment_shader"),
-                source: ShaderSource::Wgsl(fragment_shader_source),
-            })
+                source: ShaderSource::Wgsl(Cow::Owned(fragment_shader_source)),
+            });
+            create_render_pipeline(
+                device,
+                &RenderPipelineSpec {
+                    layout_label: "render_pipeline_layout",
+                    bind_group_layouts: &[parameters_bind_group_layout],
+                    push_constant_ranges: &push_constant_ranges,
+                    label: "render_pipeline",
+                    vertex_shader,
+                    fragment_shader: &fragment_shader,
+                    fragment_entry_point: "fragment_main",
+                    texture_formats: &[*format, SsrGraphics::NORMAL_DEPTH_FORMAT],
+                },
+            )
         })
-        .context("failed to validate fragment shader source")?;
-        let render_pipeline = create_render_pipeline(
-            device,
-            "render_pipeline_layout",
-            parameters_bind_group_layout,
-            "render_pipeline",
-            vertex_shader,
-            &fragment_shader,
-            BlitGraphics::RENDER_TEXTURE_FORMAT,
-        );
-        Ok(Self { render_pipeline })
+        .context("failed to validate fragment shader");
+        let compilation_meta = ShaderCompilationMeta::new(&source_for_hash, compile_start);
+        let ShaderCompilationMeta {
+            source_hash,
+            compile_duration_ms,
+            ..
+        } = compilation_meta;
+        let outcome = if render_pipeline.is_ok() {
+            "compiled"
+        } else {
+            "failed to compile"
+        };
+        eprintln!("{outcome} fragment shader {source_hash:016x} in {compile_duration_ms} ms");
+        let render_pipeline = render_pipeline?;
+        Ok(Self {
+            render_pipeline,
+            compilation_meta,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blit_graphics::BlitGraphics;
+    use pollster::block_on;
+    use wgpu::{Instance, Limits, RequestAdapterOptions};
+
+    // machines without any adapter cannot compile shaders, so there is nothing to test
+    fn test_inputs() -> Option<ReloadableInputs> {
+        block_on(Instance::default().request_adapter(&RequestAdapterOptions::default())).ok()?;
+        let persistent = block_on(PersistentGraphics::init_off_screen(
+            16,
+            16,
+            None,
+            Limits::default(),
+        ))
+        .unwrap();
+        Some(ReloadableInputs::new(
+            &persistent,
+            BlitGraphics::default_format(),
+        ))
+    }
+
+    // the smallest fragment shader that fits the render pipeline
+    const MINIMAL_SOURCE: &str = "
+        struct FastParameters {
+            camera_matrix: mat4x4<f32>,
+            time: f32,
+        }
+
+        struct Parameters {
+            camera_matrix: mat4x4<f32>,
+            time: f32,
+        }
+
+        @group(0) @binding(0) var<uniform> parameters: Parameters;
+
+        struct FragmentOutput {
+            @location(0) color: vec4<f32>,
+            @location(1) normal_depth: vec4<f32>,
+        }
+
+        @fragment
+        fn fragment_main() -> FragmentOutput {
+            let time = load_fast_parameters().time + parameters.time;
+            return FragmentOutput(vec4(time), vec4(0));
+        }
+    ";
+
+    fn compile(source: &str) -> Option<Result<ReloadableGraphics>> {
+        let inputs = test_inputs()?;
+        Some(ReloadableGraphics::compile_source(
+            &inputs,
+            source.to_owned(),
+        ))
+    }
+
+    #[test]
+    fn minimal_source_compiles() {
+        if let Some(result) = compile(MINIMAL_SOURCE) {
+            result.unwrap();
+        }
+    }
+
+    #[test]
+    fn invalid_source_fails_to_compile() {
+        if let Some(result) = compile("fn broken(") {
+            assert!(result.is_err());
+        }
+    }
+
+    // these are valid shader modules, so only creating the render pipeline catches them
+
+    #[test]
+    fn missing_entry_point_fails_to_compile() {
+        let source = MINIMAL_SOURCE.replace("fn fragment_main", "fn other_main");
+        if let Some(result) = compile(&source) {
+            assert!(result.is_err());
+        }
+    }
+
+    #[test]
+    fn wrong_output_type_fails_to_compile() {
+        let source = MINIMAL_SOURCE
+            .replace("normal_depth: vec4<f32>", "normal_depth: vec4<u32>")
+            .replace("vec4(0))", "vec4(0u))");
+        if let Some(result) = compile(&source) {
+            assert!(result.is_err());
+        }
+    }
+
+    #[test]
+    fn wrong_binding_fails_to_compile() {
+        let source = MINIMAL_SOURCE.replace("@binding(0)", "@binding(1)");
+        if let Some(result) = compile(&source) {
+            assert!(result.is_err());
+        }
     }
 }
@@ -0,0 +1,32 @@
+use std::{error::Error, fmt};
+use wgpu::SurfaceError;
+
+#[derive(Debug)]
+pub enum RenderError {
+    Surface(SurfaceError),
+    Other(anyhow::Error),
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Surface(_) => write!(f, "failed to get frame texture"),
+            Self::Other(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl Error for RenderError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Surface(error) => Some(error),
+            Self::Other(error) => error.source(),
+        }
+    }
+}
+
+impl From<anyhow::Error> for RenderError {
+    fn from(error: anyhow::Error) -> Self {
+        Self::Other(error)
+    }
+}
@@ -1,22 +1,130 @@
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpscaleMode {
+    // what the sampler does anyway, so it needs no extra pass
+    Bilinear,
+    Nearest,
+    Lanczos,
+}
+
+#[derive(Debug, Clone)]
 pub struct RenderTextureConfig {
     factor: u32,
+    aspect_override: Option<(u32, u32)>,
+    upscale_mode: UpscaleMode,
 }
 
 impl RenderTextureConfig {
-    pub fn render_texture_size(&self) -> (u32, u32) {
-        (160 * self.factor, 90 * self.factor)
+    pub fn render_texture_size(&self, surface_width: u32, surface_height: u32) -> (u32, u32) {
+        // rendering more pixels than the surface shows would only cost time
+        let Some((aspect_width, aspect_height)) = self.aspect_override else {
+            let width = (160 * self.factor).min(surface_width).max(1);
+            let height = (90 * self.factor).min(surface_height).max(1);
+            return (width, height);
+        };
+        // the blit pass letter-boxes or pillar-boxes the result to fit the surface,
+        // so both sides shrink together to keep the aspect ratio
+        let (aspect_width, aspect_height) = (aspect_width as u64, aspect_height as u64);
+        let mut width = (160 * self.factor) as u64;
+        let mut height = width * aspect_height / aspect_width;
+        if width > surface_width as u64 {
+            width = surface_width as u64;
+            height = width * aspect_height / aspect_width;
+        }
+        if height > surface_height as u64 {
+            height = surface_height as u64;
+            width = height * aspect_width / aspect_height;
+        }
+        (width.max(1) as u32, height.max(1) as u32)
+    }
+
+    pub fn set_aspect_override(&mut self, width: u32, height: u32) {
+        self.aspect_override = Some((width, height));
+    }
+
+    pub fn aspect_override(&self) -> Option<(u32, u32)> {
+        self.aspect_override
+    }
+
+    pub fn upscale_mode(&self) -> UpscaleMode {
+        self.upscale_mode
+    }
+
+    pub fn cycle_upscale_mode(&mut self) -> UpscaleMode {
+        self.upscale_mode = match self.upscale_mode {
+            UpscaleMode::Bilinear => UpscaleMode::Nearest,
+            UpscaleMode::Nearest => UpscaleMode::Lanczos,
+            UpscaleMode::Lanczos => UpscaleMode::Bilinear,
+        };
+        self.upscale_mode
     }
 
     pub fn update_render_texture_size(&mut self, delta: i32) {
         self.factor = std::cmp::max(1, self.factor.saturating_add_signed(delta));
     }
+
+    pub fn halve_render_texture_size(&mut self) -> bool {
+        let factor = std::cmp::max(1, self.factor / 2);
+        let changed = factor != self.factor;
+        self.factor = factor;
+        changed
+    }
 }
 
 impl Default for RenderTextureConfig {
     fn default() -> Self {
         Self {
             factor: 12, // 1920x1080
+            aspect_override: None,
+            upscale_mode: UpscaleMode::Bilinear,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(factor: u32, aspect_override: Option<(u32, u32)>) -> RenderTextureConfig {
+        RenderTextureConfig {
+            factor,
+            aspect_override,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn size_follows_the_factor() {
+        assert_eq!(
+            config(12, None).render_texture_size(3840, 2160),
+            (1920, 1080)
+        );
+    }
+
+    #[test]
+    fn size_is_capped_to_the_surface() {
+        assert_eq!(config(100, None).render_texture_size(800, 600), (800, 600));
+    }
+
+    #[test]
+    fn aspect_override_is_capped_to_the_surface() {
+        let (width, height) = config(100, Some((16, 9))).render_texture_size(800, 600);
+        assert_eq!((width, height), (800, 450));
+    }
+
+    #[test]
+    fn extreme_aspect_override_keeps_the_aspect_within_the_surface() {
+        let (width, height) = config(12, Some((1, 10))).render_texture_size(1920, 1080);
+        assert_eq!((width, height), (108, 1080));
+        let (width, height) = config(12, Some((10, 1))).render_texture_size(1920, 1080);
+        assert_eq!((width, height), (1920, 192));
+    }
+
+    #[test]
+    fn size_is_never_zero() {
+        assert_eq!(
+            config(1, Some((1, 10000))).render_texture_size(1, 1),
+            (1, 1)
+        );
+        assert_eq!(config(1, None).render_texture_size(0, 0), (1, 1));
+    }
+}
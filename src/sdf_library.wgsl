@@ -0,0 +1,53 @@
+fn max_component_2(a: vec2<Scalar>) -> Scalar {
+    return max(a.x, a.y);
+}
+
+fn min_component_2(a: vec2<Scalar>) -> Scalar {
+    return min(a.x, a.y);
+}
+
+fn max_component_3(a: Vector) -> Scalar {
+    return max(max_component_2(a.xy), a.z);
+}
+
+fn min_component_3(a: Vector) -> Scalar {
+    return min(min_component_2(a.xy), a.z);
+}
+
+fn box(position: Position, size: Vector) -> Distance {
+    let q = abs(position) - size;
+    return length(max(q, Vector(0))) + min(max_component_3(q), 0);
+}
+
+fn half_space(position: Position, anchor: Position, normal: Direction) -> Distance {
+    return dot(position - anchor, normal);
+}
+
+fn plane_normal(a: Position, b: Position, c: Position) -> Direction {
+    return normalize(cross(c - a, b - a));
+}
+
+fn tetrahedron(position: Position, a: Position, b: Position, c: Position, d: Position) -> Distance {
+    return max(max(max(
+        half_space(position, a, plane_normal(a, b, c)),
+        half_space(position, a, plane_normal(a, c, d))),
+        half_space(position, a, plane_normal(a, d, b))),
+        half_space(position, b, plane_normal(b, d, c)));
+}
+
+fn mirror(position: Position, anchor: Position, normal: Direction) -> Position {
+    let distance = dot(position - anchor, normal);
+    return position + (abs(distance) - distance) * normal;
+}
+
+fn repeat(position: Position) -> Position {
+    return fract(position + 0.5) - 0.5;
+}
+
+fn cross_inside(position: Position, size: Distance) -> Distance {
+    let p = abs(position);
+    let x = max_component_2(p.yz);
+    let y = max_component_2(p.zx);
+    let z = max_component_2(p.xy);
+    return min_component_3(Position(x, y, z)) - size;
+}
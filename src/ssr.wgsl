@@ -0,0 +1,117 @@
+struct Parameters {
+    camera_matrix: mat4x4<f32>,
+    aspect_scale: vec2<f32>,
+    time: f32,
+    num_iterations: u32,
+    scene_index: u32,
+    fractal_power: f32,
+    dither_enabled: u32,
+    bloom_enabled: u32,
+    bloom_threshold: f32,
+    bloom_strength: f32,
+    viewport_count: u32,
+    step_factor: f32,
+    chromatic_aberration: f32,
+    mandelbox_scale: f32,
+    mandelbox_fold_radius: f32,
+    background_color_top: vec3<f32>,
+    background_color_bottom: vec3<f32>,
+    interior_mode: u32,
+    orbit_target: vec3<f32>,
+    show_orbit_target: u32,
+    normal_epsilon: f32,
+    camera_matrix_b: mat4x4<f32>,
+    blend_views: f32,
+    jitter: vec2<f32>,
+    jitter_enabled: u32,
+    wall_time: f32,
+    sharpen_strength: f32,
+    film_grain: f32,
+    seed: u32,
+    debug_mode: u32,
+    letterbox_scale: vec2<f32>,
+    scanline_strength: f32,
+    ssr_strength: f32,
+    time_b: f32,
+}
+
+@group(0) @binding(0)
+var texture: texture_2d<f32>;
+@group(0) @binding(1)
+var texture_sampler: sampler;
+@group(1) @binding(0)
+var<uniform> parameters: Parameters;
+// view space normals and depths written by fragment.wgsl, a depth of zero means nothing was hit
+@group(2) @binding(0)
+var normal_depth_texture: texture_2d<f32>;
+@group(2) @binding(1)
+var normal_depth_sampler: sampler;
+
+// the same projection as in fragment.wgsl
+const PI = 3.141592653589793238;
+const FOV_DEGREES = 90;
+const CAMERA_DIRECTION_Z = 1 / atan(FOV_DEGREES * PI / 180);
+
+const MAX_STEPS = 48;
+const MAX_DISTANCE = 2.0;
+// how far behind a surface the ray may be to still count as hitting it
+const THICKNESS = 0.05;
+
+fn viewport_aspect_scale() -> vec2<f32> {
+    // the viewports split the width, the shorter side of each one spans [-1, 1]
+    let scale = parameters.aspect_scale * vec2(1 / f32(parameters.viewport_count), 1);
+    return scale / min(scale.x, scale.y);
+}
+
+fn load_normal_depth(uv: vec2<f32>) -> vec4<f32> {
+    let size = textureDimensions(normal_depth_texture);
+    let pixel = min(vec2<u32>(uv * vec2<f32>(size)), size - 1);
+    return textureLoad(normal_depth_texture, pixel, 0);
+}
+
+@fragment
+fn fragment_main(@location(0) screen_position: vec2<f32>) -> @location(0) vec4<f32> {
+    let flipped_uv = (screen_position + 1) * 0.5;
+    let uv = vec2(flipped_uv.x, 1 - flipped_uv.y);
+    let normal_depth = load_normal_depth(uv);
+    if (normal_depth.w <= 0) {
+        return vec4(0, 0, 0, 1);
+    }
+    // the position within the viewport the pixel belongs to
+    let viewport_count = f32(parameters.viewport_count);
+    let viewport_index = min(floor(uv.x * viewport_count), viewport_count - 1);
+    let viewport_x = uv.x * viewport_count - viewport_index;
+    let viewport_position = vec2(viewport_x * 2 - 1, screen_position.y);
+    let aspect_scale = viewport_aspect_scale();
+    let view_direction = normalize(vec3(viewport_position * aspect_scale, CAMERA_DIRECTION_Z));
+    let position = view_direction * (normal_depth.w / view_direction.z);
+    let reflected = reflect(view_direction, normalize(normal_depth.xyz));
+    let step = reflected * (MAX_DISTANCE / MAX_STEPS);
+    for (var i = 1; i <= MAX_STEPS; i++) {
+        let sample_position = position + f32(i) * step;
+        if (sample_position.z <= 0) {
+            break;
+        }
+        let sample_viewport_position = sample_position.xy * CAMERA_DIRECTION_Z / sample_position.z / aspect_scale;
+        // nothing is known about what is outside of the viewport
+        if (any(abs(sample_viewport_position) > vec2(1))) {
+            break;
+        }
+        let sample_uv = vec2(
+            (sample_viewport_position.x * 0.5 + 0.5 + viewport_index) / viewport_count,
+            0.5 - sample_viewport_position.y * 0.5,
+        );
+        let scene_normal_depth = load_normal_depth(sample_uv);
+        let behind = sample_position.z - scene_normal_depth.w;
+        // surfaces facing away from the ray are only passed behind, not hit
+        let faces_ray = dot(scene_normal_depth.xyz, reflected) < 0;
+        if (scene_normal_depth.w > 0 && behind > 0 && behind < THICKNESS && faces_ray) {
+            let color = textureSampleLevel(texture, texture_sampler, sample_uv, 0).rgb;
+            // fades out towards the edges, where the reflections would end abruptly
+            let edge_distance = 1 - max(abs(sample_viewport_position.x), abs(sample_viewport_position.y));
+            let fade = saturate(edge_distance * 4) * (1 - f32(i) / MAX_STEPS);
+            return vec4(color * fade, 1);
+        }
+    }
+    return vec4(0, 0, 0, 1);
+}
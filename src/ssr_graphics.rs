@@ -0,0 +1,138 @@
+use crate::{
+    blit_graphics::BlitGraphics,
+    persistent_graphics::PersistentGraphics,
+    utils::{RenderPipelineSpec, create_render_pipeline},
+};
+use std::borrow::Cow;
+use wgpu::{
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindingResource, Device, Extent3d,
+    RenderPipeline, ShaderModuleDescriptor, ShaderSource, Texture, TextureDescriptor,
+    TextureDimension, TextureFormat, TextureUsages, TextureView, TextureViewDescriptor,
+};
+
+#[derive(Debug)]
+pub struct SsrPipelines {
+    pub render_pipeline: RenderPipeline,
+}
+
+impl SsrPipelines {
+    pub fn init(persistent: &PersistentGraphics, format: TextureFormat) -> Self {
+        let PersistentGraphics {
+            device,
+            vertex_shader,
+            blit_bind_group_layout,
+            parameters_bind_group_layout,
+            ..
+        } = persistent;
+        let ssr_fragment_shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("ssr_fragment_shader"),
+            source: ShaderSource::Wgsl(Cow::Borrowed(include_str!("./ssr.wgsl"))),
+        });
+        let render_pipeline = create_render_pipeline(
+            device,
+            &RenderPipelineSpec {
+                layout_label: "ssr_render_pipeline_layout",
+                // the render texture, the parameters and the normals and depths
+                bind_group_layouts: &[
+                    blit_bind_group_layout,
+                    parameters_bind_group_layout,
+                    blit_bind_group_layout,
+                ],
+                push_constant_ranges: &[],
+                label: "ssr_render_pipeline",
+                vertex_shader,
+                fragment_shader: &ssr_fragment_shader,
+                fragment_entry_point: "fragment_main",
+                texture_formats: &[format],
+            },
+        );
+        Self { render_pipeline }
+    }
+}
+
+// screen-space reflections of the render texture, composited in the blit pass
+#[derive(Debug)]
+pub struct SsrGraphics {
+    pub normal_depth_texture: Texture,
+    pub reflection_texture_view: TextureView,
+    pub normal_depth_bind_group: BindGroup,
+    pub composite_bind_group: BindGroup,
+}
+
+impl SsrGraphics {
+    // holds the view space normal and depth, so it needs more precision than the colors
+    pub const NORMAL_DEPTH_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+    // written by the render pass as a second color attachment
+    pub fn create_normal_depth_texture(device: &Device, width: u32, height: u32) -> Texture {
+        device.create_texture(&TextureDescriptor {
+            label: Some("ssr_normal_depth_texture"),
+            dimension: TextureDimension::D2,
+            size: Extent3d {
+                width,
+                height,
+                ..Default::default()
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            format: Self::NORMAL_DEPTH_FORMAT,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        })
+    }
+
+    pub fn init(persistent: &PersistentGraphics, blit: &BlitGraphics) -> Self {
+        let PersistentGraphics {
+            device,
+            render_texture_sampler,
+            blit_bind_group_layout,
+            ..
+        } = persistent;
+        let (width, height) = blit.render_texture_size();
+        let normal_depth_texture = Self::create_normal_depth_texture(device, width, height);
+        let reflection_texture = device.create_texture(&TextureDescriptor {
+            label: Some("ssr_reflection_texture"),
+            dimension: TextureDimension::D2,
+            size: Extent3d {
+                width,
+                height,
+                ..Default::default()
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            format: blit.format(),
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = |texture: &Texture| texture.create_view(&TextureViewDescriptor::default());
+        let create_texture_bind_group = |label, texture_view: &TextureView| {
+            device.create_bind_group(&BindGroupDescriptor {
+                label: Some(label),
+                layout: blit_bind_group_layout,
+                entries: &[
+                    BindGroupEntry {
+                        binding: 0,
+                        resource: BindingResource::TextureView(texture_view),
+                    },
+                    BindGroupEntry {
+                        binding: 1,
+                        resource: BindingResource::Sampler(render_texture_sampler),
+                    },
+                ],
+            })
+        };
+        let reflection_texture_view = view(&reflection_texture);
+        Self {
+            normal_depth_bind_group: create_texture_bind_group(
+                "ssr_normal_depth_bind_group",
+                &view(&normal_depth_texture),
+            ),
+            composite_bind_group: create_texture_bind_group(
+                "ssr_composite_bind_group",
+                &reflection_texture_view,
+            ),
+            normal_depth_texture,
+            reflection_texture_view,
+        }
+    }
+}
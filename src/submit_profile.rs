@@ -0,0 +1,111 @@
+use std::{cell::Cell, time::Duration};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubmitPath {
+    // all passes in one command buffer
+    Single,
+    // the ray marching passes are submitted before the surface texture is acquired for the blit
+    Split,
+}
+
+impl SubmitPath {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Single => "single submission",
+            Self::Split => "split submission",
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Samples {
+    count: u32,
+    total: Duration,
+}
+
+impl Samples {
+    fn average(self) -> Duration {
+        self.total / self.count.max(1)
+    }
+}
+
+// alternates between both submit paths until enough GPU frame times are measured for each,
+// logs which one is faster and sticks with the split submission afterwards
+#[derive(Debug, Default)]
+pub struct SubmitProfile {
+    // the paths take turns per measured frame, as only every few frames can be measured
+    measured_frame_count: Cell<u32>,
+    // the path of the frame whose GPU timestamps are being read back
+    pending_path: Cell<Option<SubmitPath>>,
+    single: Cell<Samples>,
+    split: Cell<Samples>,
+    is_finished: Cell<bool>,
+}
+
+impl SubmitProfile {
+    const SAMPLES_PER_PATH: u32 = 120;
+
+    pub fn is_profiling(&self) -> bool {
+        !self.is_finished.get()
+    }
+
+    pub fn stop(&self) {
+        self.is_finished.set(true);
+    }
+
+    pub fn next_path(&self) -> SubmitPath {
+        if !self.is_profiling() {
+            return SubmitPath::Split;
+        }
+        if self.measured_frame_count.get().is_multiple_of(2) {
+            SubmitPath::Single
+        } else {
+            SubmitPath::Split
+        }
+    }
+
+    pub fn set_pending_path(&self, path: SubmitPath) {
+        self.pending_path.set(Some(path));
+        self.measured_frame_count
+            .set(self.measured_frame_count.get().wrapping_add(1));
+    }
+
+    // attributes a finished measurement to the path of the frame it was taken in
+    pub fn record(&self, frame_duration: Option<Duration>) {
+        let Some(frame_duration) = frame_duration else {
+            return;
+        };
+        let Some(path) = self.pending_path.take() else {
+            return;
+        };
+        let samples = match path {
+            SubmitPath::Single => &self.single,
+            SubmitPath::Split => &self.split,
+        };
+        let mut updated = samples.get();
+        updated.count += 1;
+        updated.total += frame_duration;
+        samples.set(updated);
+        let (single, split) = (self.single.get(), self.split.get());
+        if single.count >= Self::SAMPLES_PER_PATH && split.count >= Self::SAMPLES_PER_PATH {
+            self.log_result(single.average(), split.average());
+            self.stop();
+        }
+    }
+
+    fn log_result(&self, single: Duration, split: Duration) {
+        let faster = if split < single {
+            SubmitPath::Split
+        } else {
+            SubmitPath::Single
+        };
+        eprintln!(
+            "GPU frame time with {}: {:.3} ms, with {}: {:.3} ms, {} is faster",
+            SubmitPath::Single.name(),
+            single.as_secs_f64() * 1000.0,
+            SubmitPath::Split.name(),
+            split.as_secs_f64() * 1000.0,
+            faster.name(),
+        );
+    }
+}
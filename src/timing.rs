@@ -1,12 +1,61 @@
-use crate::{parameters::Parameters, utils::limited_quadratric_delta};
-use std::time::{Duration, Instant};
+use crate::{
+    camera::Camera,
+    parameters::Parameters,
+    utils::{limited_quadratic_delta, percentile},
+};
+use anyhow::{Context, Result};
+use std::{
+    f32::consts::PI,
+    fs::{File, OpenOptions},
+    io::{BufWriter, Write},
+    path::Path,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
 #[derive(Debug)]
 pub struct Timing {
     time_factor: f32,
+    time_factor_limits: (f32, f32),
+    // drives the secondary time in the parameters, which animates the shapes
+    time_factor_b: f32,
+    step_mode: StepMode,
+    time_mode: TimeMode,
+    start_time: Instant,
     last_frame_time: Instant,
     last_fps_log: Instant,
     frames_since_last_fps_log: u32,
+    logged_fps_this_frame: bool,
+    fragment_shader_invocations: Option<u64>,
+    // the latest measurement, GPU timings arrive a few frames late and not for every frame
+    gpu_frame_duration: Option<Duration>,
+    log_file: Option<BufWriter<File>>,
+    session_frame_times: Vec<f32>,
+    frame_budget_ms: f32,
+    // in this session, like the frame times
+    deadline_misses: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SessionStats {
+    pub total_frames: u32,
+    pub session_duration_s: f32,
+    pub mean_fps: f32,
+    pub p50_fps: f32,
+    pub p99_fps: f32,
+    pub min_fps: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StepMode {
+    RealTime,
+    FixedStep(f32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimeMode {
+    Absolute,
+    // wraps around after the period, so the time keeps its precision in long sessions
+    Cyclic(f32),
 }
 
 impl Timing {
@@ -14,39 +63,335 @@ impl Timing {
         let start_time = Instant::now();
         Self {
             time_factor: 1.0,
+            time_factor_limits: Self::DEFAULT_TIME_FACTOR_LIMITS,
+            time_factor_b: 1.0,
+            step_mode: StepMode::RealTime,
+            time_mode: TimeMode::Absolute,
+            start_time,
             last_frame_time: start_time,
             last_fps_log: start_time,
             frames_since_last_fps_log: 0,
+            logged_fps_this_frame: false,
+            fragment_shader_invocations: None,
+            gpu_frame_duration: None,
+            log_file: None,
+            session_frame_times: Vec::new(),
+            frame_budget_ms: Self::DEFAULT_FRAME_BUDGET_MS,
+            deadline_misses: 0,
         }
     }
 
+    pub fn set_log_file(&mut self, path: &Path) -> Result<()> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("failed to open FPS log file at {}", path.display()))?;
+        let is_empty = file
+            .metadata()
+            .context("failed to stat FPS log file")?
+            .len()
+            == 0;
+        let mut log_file = BufWriter::new(file);
+        if is_empty {
+            writeln!(log_file, "timestamp_s,fps,gpu_ms").context("failed to write CSV header")?;
+        }
+        self.log_file = Some(log_file);
+        Ok(())
+    }
+
     pub fn update(&mut self, parameters: &mut Parameters) -> Duration {
         let now = Instant::now();
-        let delta_time = now - self.last_frame_time;
+        let frame_time = (now - self.last_frame_time).as_secs_f32();
+        self.session_frame_times.push(frame_time);
+        if frame_time * 1000.0 > self.frame_budget_ms {
+            self.deadline_misses += 1;
+        }
+        // capped like the camera's, so animations do not jump after a pause either
+        let delta_time = match self.step_mode {
+            StepMode::RealTime => (now - self.last_frame_time).min(Camera::MAX_DELTA_TIME),
+            StepMode::FixedStep(fps) => Duration::from_secs_f64(1.0 / fps as f64),
+        };
         self.last_frame_time = now;
         parameters.update_time(self.time_factor * delta_time.as_secs_f32());
+        parameters.update_time_b(self.time_factor_b * delta_time.as_secs_f32());
+        if let TimeMode::Cyclic(period) = self.time_mode {
+            parameters.wrap_time(period);
+            parameters.wrap_time_b(period);
+        }
+        parameters.update_wall_time(self.wall_time_secs());
         self.update_fps(now);
         delta_time
     }
 
+    pub fn wall_time_secs(&self) -> f32 {
+        self.start_time.elapsed().as_secs_f32()
+    }
+
+    // negative factors run time backwards
+    const DEFAULT_TIME_FACTOR_LIMITS: (f32, f32) = (-8.0, 8.0);
+
+    pub fn set_time_factor_limits(&mut self, min: f32, max: f32) {
+        self.time_factor_limits = (min, max);
+        self.time_factor = self.time_factor.clamp(min, max);
+    }
+
+    pub fn time_factor(&self) -> f32 {
+        self.time_factor
+    }
+
     pub fn update_time_factor(&mut self, delta: f32) {
-        self.time_factor += limited_quadratric_delta(self.time_factor, delta);
+        let (min, max) = self.time_factor_limits;
+        self.time_factor =
+            (self.time_factor + limited_quadratic_delta(self.time_factor, delta)).clamp(min, max);
+    }
+
+    pub fn reset_time_factor(&mut self) {
+        self.set_time_factor(1.0);
+    }
+
+    pub fn set_time_factor(&mut self, time_factor: f32) {
+        let (min, max) = self.time_factor_limits;
+        self.time_factor = time_factor.clamp(min, max);
     }
 
     pub fn stop_time(&mut self) {
         self.time_factor = 0.0;
     }
 
+    pub fn time_factor_b(&self) -> f32 {
+        self.time_factor_b
+    }
+
+    // shares the limits of the primary time factor
+    pub fn update_time_factor_b(&mut self, delta: f32) {
+        let (min, max) = self.time_factor_limits;
+        self.time_factor_b = (self.time_factor_b
+            + limited_quadratic_delta(self.time_factor_b, delta))
+        .clamp(min, max);
+    }
+
+    pub fn stop_time_b(&mut self) {
+        self.time_factor_b = 0.0;
+    }
+
+    pub const DEFAULT_FIXED_STEP_FPS: f32 = 60.0;
+
+    pub fn step_mode(&self) -> StepMode {
+        self.step_mode
+    }
+
+    pub fn set_step_mode(&mut self, step_mode: StepMode) {
+        self.step_mode = step_mode;
+    }
+
+    pub fn toggle_step_mode(&mut self) {
+        self.step_mode = match self.step_mode {
+            StepMode::RealTime => StepMode::FixedStep(Self::DEFAULT_FIXED_STEP_FPS),
+            StepMode::FixedStep(_) => StepMode::RealTime,
+        };
+    }
+
+    // a multiple of the animation period in fragment.wgsl, so wrapping around is seamless
+    const CYCLIC_TIME_PERIOD: f32 = 20.0 * PI;
+
+    pub fn time_mode(&self) -> TimeMode {
+        self.time_mode
+    }
+
+    pub fn toggle_time_mode(&mut self) {
+        self.time_mode = match self.time_mode {
+            TimeMode::Absolute => TimeMode::Cyclic(Self::CYCLIC_TIME_PERIOD),
+            TimeMode::Cyclic(_) => TimeMode::Absolute,
+        };
+    }
+
+    // 60 FPS
+    const DEFAULT_FRAME_BUDGET_MS: f32 = 1000.0 / 60.0;
+
+    pub fn set_frame_budget_ms(&mut self, ms: f32) {
+        self.frame_budget_ms = ms;
+    }
+
+    // the fraction of frames in this session that took longer than the frame budget
+    pub fn deadline_miss_rate(&self) -> f32 {
+        if self.session_frame_times.is_empty() {
+            return 0.0;
+        }
+        self.deadline_misses as f32 / self.session_frame_times.len() as f32
+    }
+
+    pub fn set_fragment_shader_invocations(&mut self, invocations: Option<u64>) {
+        self.fragment_shader_invocations = invocations;
+    }
+
+    pub fn record_gpu_frame_duration(&mut self, frame_duration: Option<Duration>) {
+        if frame_duration.is_some() {
+            self.gpu_frame_duration = frame_duration;
+        }
+    }
+
     const FPS_LOG_INTERVAL: Duration = Duration::from_secs(1);
 
+    // true once per FPS log interval, for work that does not need to happen every frame
+    pub fn logged_fps_this_frame(&self) -> bool {
+        self.logged_fps_this_frame
+    }
+
     fn update_fps(&mut self, now: Instant) {
         self.frames_since_last_fps_log += 1;
         let time_since_last_fps_log = now - self.last_fps_log;
-        if time_since_last_fps_log >= Self::FPS_LOG_INTERVAL {
+        self.logged_fps_this_frame = time_since_last_fps_log >= Self::FPS_LOG_INTERVAL;
+        if self.logged_fps_this_frame {
             let fps = self.frames_since_last_fps_log as f32 / time_since_last_fps_log.as_secs_f32();
-            eprintln!("{fps:.1} FPS");
+            let deadline_misses = format!(
+                "{} frames over the {:.1} ms budget",
+                self.deadline_misses, self.frame_budget_ms
+            );
+            match self.fragment_shader_invocations {
+                Some(invocations) => eprintln!(
+                    "{fps:.1} FPS, {invocations} fragment shader invocations per frame, {deadline_misses}"
+                ),
+                None => eprintln!("{fps:.1} FPS, {deadline_misses}"),
+            }
+            self.log_fps(fps);
             self.last_fps_log = now;
             self.frames_since_last_fps_log = 0;
         }
     }
+
+    pub fn session_stats(&self) -> Option<SessionStats> {
+        if self.session_frame_times.is_empty() {
+            return None;
+        }
+        let mut frame_times = self.session_frame_times.clone();
+        frame_times.sort_by(f32::total_cmp);
+        let percentile = |fraction| percentile(&frame_times, fraction);
+        let total_frames = frame_times.len() as u32;
+        let session_duration_s = frame_times.iter().sum();
+        Some(SessionStats {
+            total_frames,
+            session_duration_s,
+            mean_fps: total_frames as f32 / session_duration_s,
+            p50_fps: 1.0 / percentile(0.5),
+            // the slowest frames have the lowest frame rates
+            p99_fps: 1.0 / percentile(0.99),
+            min_fps: 1.0 / percentile(1.0),
+        })
+    }
+
+    pub fn print_session_stats(&self) {
+        let Some(stats) = self.session_stats() else {
+            return;
+        };
+        let SessionStats {
+            total_frames,
+            session_duration_s,
+            mean_fps,
+            p50_fps,
+            p99_fps,
+            min_fps,
+        } = stats;
+        eprintln!(
+            "{total_frames} frames in {session_duration_s:.1} s: {mean_fps:.1} FPS mean, {p50_fps:.1} FPS median, {p99_fps:.1} FPS 99th percentile, {min_fps:.1} FPS min, {:.1} % over the {:.1} ms budget",
+            self.deadline_miss_rate() * 100.0,
+            self.frame_budget_ms
+        );
+    }
+
+    pub fn clear_session_stats(&mut self) {
+        self.session_frame_times.clear();
+        self.deadline_misses = 0;
+    }
+
+    // the gpu_ms column stays empty without timestamp queries
+    fn fps_log_line(timestamp: f64, fps: f32, gpu_frame_duration: Option<Duration>) -> String {
+        let gpu_ms = gpu_frame_duration
+            .map(|duration| format!("{:.3}", duration.as_secs_f64() * 1000.0))
+            .unwrap_or_default();
+        format!("{timestamp:.3},{fps:.1},{gpu_ms}")
+    }
+
+    fn log_fps(&mut self, fps: f32) {
+        let Some(log_file) = &mut self.log_file else {
+            return;
+        };
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64();
+        let line = Self::fps_log_line(timestamp, fps, self.gpu_frame_duration);
+        if let Err(error) = writeln!(log_file, "{line}") {
+            eprintln!("failed to write to FPS log file: {error}");
+            self.log_file = None;
+        }
+    }
+}
+
+impl Drop for Timing {
+    fn drop(&mut self) {
+        self.print_session_stats();
+        if let Some(log_file) = &mut self.log_file
+            && let Err(error) = log_file.flush()
+        {
+            eprintln!("failed to flush FPS log file: {error}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fps_log_line_contains_the_gpu_time() {
+        let line = Timing::fps_log_line(12.5, 59.94, Some(Duration::from_micros(4250)));
+        assert_eq!(line, "12.500,59.9,4.250");
+    }
+
+    #[test]
+    fn fps_log_line_leaves_the_gpu_time_empty_without_measurements() {
+        assert_eq!(Timing::fps_log_line(12.5, 60.0, None), "12.500,60.0,");
+    }
+
+    #[test]
+    fn missing_gpu_measurements_keep_the_latest_one() {
+        let mut timing = Timing::init();
+        timing.record_gpu_frame_duration(Some(Duration::from_millis(3)));
+        timing.record_gpu_frame_duration(None);
+        assert_eq!(timing.gpu_frame_duration, Some(Duration::from_millis(3)));
+    }
+
+    #[test]
+    fn time_factor_limits_clamp_the_current_factor() {
+        let mut timing = Timing::init();
+        timing.set_time_factor(6.0);
+        timing.set_time_factor_limits(-2.0, 4.0);
+        assert_eq!(timing.time_factor(), 4.0);
+    }
+
+    #[test]
+    fn set_time_factors_stay_within_the_limits() {
+        let mut timing = Timing::init();
+        timing.set_time_factor_limits(2.0, 4.0);
+        timing.set_time_factor(-6.0);
+        assert_eq!(timing.time_factor(), 2.0);
+        timing.reset_time_factor();
+        assert_eq!(timing.time_factor(), 2.0);
+    }
+
+    #[test]
+    fn time_factor_updates_stay_within_the_limits() {
+        let mut timing = Timing::init();
+        timing.set_time_factor_limits(-2.0, 4.0);
+        // each update only steps a limited amount
+        for _ in 0..100 {
+            timing.update_time_factor(100.0);
+        }
+        assert_eq!(timing.time_factor(), 4.0);
+        for _ in 0..100 {
+            timing.update_time_factor(-100.0);
+        }
+        assert_eq!(timing.time_factor(), -2.0);
+    }
 }
@@ -0,0 +1,55 @@
+@group(0) @binding(0)
+var texture: texture_2d<f32>;
+@group(0) @binding(1)
+var texture_sampler: sampler;
+
+// in render texture texels, with texel centers at whole numbers
+fn texel_position(screen_position: vec2<f32>) -> vec2<f32> {
+    let flipped_uv = (screen_position + 1) * 0.5;
+    let uv = vec2(flipped_uv.x, 1 - flipped_uv.y);
+    return uv * vec2<f32>(textureDimensions(texture)) - 0.5;
+}
+
+fn load_clamped(texel: vec2<f32>) -> vec4<f32> {
+    let max_texel = vec2<i32>(textureDimensions(texture)) - 1;
+    return textureLoad(texture, clamp(vec2<i32>(texel), vec2(0), max_texel), 0);
+}
+
+// windowed sinc with two lobes
+fn lanczos_weight(x: f32) -> f32 {
+    const PI = 3.141592653589793238;
+    const RADIUS = 2.0;
+    if (abs(x) < 1e-5) {
+        return 1;
+    }
+    if (abs(x) >= RADIUS) {
+        return 0;
+    }
+    let pi_x = PI * x;
+    return RADIUS * sin(pi_x) * sin(pi_x / RADIUS) / (pi_x * pi_x);
+}
+
+@fragment
+fn nearest_main(@location(0) screen_position: vec2<f32>) -> @location(0) vec4<f32> {
+    return load_clamped(round(texel_position(screen_position)));
+}
+
+// 5 taps per axis around the nearest texel, so the kernel radius is always covered
+@fragment
+fn lanczos_main(@location(0) screen_position: vec2<f32>) -> @location(0) vec4<f32> {
+    let position = texel_position(screen_position);
+    let center = round(position);
+    var color = vec4<f32>(0);
+    var total_weight = 0.0;
+    for (var y = -2; y <= 2; y++) {
+        for (var x = -2; x <= 2; x++) {
+            let texel = center + vec2(f32(x), f32(y));
+            let offset = position - texel;
+            let weight = lanczos_weight(offset.x) * lanczos_weight(offset.y);
+            color += weight * load_clamped(texel);
+            total_weight += weight;
+        }
+    }
+    // the negative lobes can overshoot, which would show as colored halos
+    return max(color / total_weight, vec4(0));
+}
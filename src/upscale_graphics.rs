@@ -0,0 +1,116 @@
+use crate::{
+    blit_graphics::BlitGraphics,
+    persistent_graphics::PersistentGraphics,
+    utils::{RenderPipelineSpec, create_render_pipeline},
+};
+use std::borrow::Cow;
+use wgpu::{
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindingResource, Extent3d, RenderPipeline,
+    ShaderModuleDescriptor, ShaderSource, TextureDescriptor, TextureDimension, TextureFormat,
+    TextureUsages, TextureView, TextureViewDescriptor,
+};
+
+#[derive(Debug)]
+pub struct UpscalePipelines {
+    pub nearest_render_pipeline: RenderPipeline,
+    pub lanczos_render_pipeline: RenderPipeline,
+}
+
+impl UpscalePipelines {
+    pub fn init(persistent: &PersistentGraphics, format: TextureFormat) -> Self {
+        let PersistentGraphics {
+            device,
+            vertex_shader,
+            blit_bind_group_layout,
+            ..
+        } = persistent;
+        let upscale_fragment_shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("upscale_fragment_shader"),
+            source: ShaderSource::Wgsl(Cow::Borrowed(include_str!("./upscale.wgsl"))),
+        });
+        let create_upscale_render_pipeline = |label, fragment_entry_point| {
+            create_render_pipeline(
+                device,
+                &RenderPipelineSpec {
+                    layout_label: "upscale_render_pipeline_layout",
+                    bind_group_layouts: &[blit_bind_group_layout],
+                    push_constant_ranges: &[],
+                    label,
+                    vertex_shader,
+                    fragment_shader: &upscale_fragment_shader,
+                    fragment_entry_point,
+                    texture_formats: &[format],
+                },
+            )
+        };
+        Self {
+            nearest_render_pipeline: create_upscale_render_pipeline(
+                "upscale_nearest_render_pipeline",
+                "nearest_main",
+            ),
+            lanczos_render_pipeline: create_upscale_render_pipeline(
+                "upscale_lanczos_render_pipeline",
+                "lanczos_main",
+            ),
+        }
+    }
+}
+
+// the render texture filtered up to the surface size, sampled by the blit pass instead of it
+#[derive(Debug)]
+pub struct UpscaleGraphics {
+    pub upscaled_texture_view: TextureView,
+    pub upscaled_bind_group: BindGroup,
+    size: (u32, u32),
+}
+
+impl UpscaleGraphics {
+    pub fn size(&self) -> (u32, u32) {
+        self.size
+    }
+
+    pub fn init(persistent: &PersistentGraphics, blit: &BlitGraphics) -> Self {
+        let PersistentGraphics {
+            device,
+            render_texture_sampler,
+            blit_bind_group_layout,
+            ..
+        } = persistent;
+        let size = persistent.surface_size();
+        let (width, height) = size;
+        let upscaled_texture = device.create_texture(&TextureDescriptor {
+            label: Some("upscaled_texture"),
+            dimension: TextureDimension::D2,
+            size: Extent3d {
+                width: width.max(1),
+                height: height.max(1),
+                ..Default::default()
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            format: blit.format(),
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let upscaled_texture_view = upscaled_texture.create_view(&TextureViewDescriptor::default());
+        let upscaled_bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("upscaled_bind_group"),
+            layout: blit_bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(&upscaled_texture_view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(render_texture_sampler),
+                },
+            ],
+        });
+        Self {
+            upscaled_texture_view,
+            upscaled_bind_group,
+            size,
+        }
+    }
+}
@@ -1,39 +1,51 @@
-use anyhow::Result;
+use anyhow::{Context, Result, bail};
 use pollster::block_on;
+use std::{fs::File, io::BufWriter, path::Path};
 use wgpu::{
-    BindGroupLayout, Device, Error, ErrorFilter, FragmentState, MultisampleState,
-    PipelineCompilationOptions, PipelineLayoutDescriptor, PrimitiveState, PrimitiveTopology,
-    RenderPipeline, RenderPipelineDescriptor, ShaderModule, TextureFormat, VertexState,
+    BindGroupLayout, BufferDescriptor, BufferUsages, COPY_BYTES_PER_ROW_ALIGNMENT,
+    CommandEncoderDescriptor, ComputePipeline, ComputePipelineDescriptor, Device, Error,
+    ErrorFilter, FragmentState, MapMode, MultisampleState, PipelineCompilationOptions,
+    PipelineLayoutDescriptor, PollType, PrimitiveState, PrimitiveTopology, PushConstantRange,
+    Queue, RenderPipeline, RenderPipelineDescriptor, ShaderModule, SurfaceCapabilities,
+    TexelCopyBufferInfo, TexelCopyBufferLayout, Texture, TextureFormat, VertexState,
 };
 
-pub fn create_render_pipeline(
-    device: &Device,
-    layout_label: &'static str,
-    bind_group_layout: &BindGroupLayout,
-    label: &'static str,
-    vertex_shader: &ShaderModule,
-    fragment_shader: &ShaderModule,
-    texture_format: TextureFormat,
-) -> RenderPipeline {
+// everything that differs between the full-screen render pipelines
+pub struct RenderPipelineSpec<'a> {
+    pub layout_label: &'static str,
+    pub bind_group_layouts: &'a [&'a BindGroupLayout],
+    pub push_constant_ranges: &'a [PushConstantRange],
+    pub label: &'static str,
+    pub vertex_shader: &'a ShaderModule,
+    pub fragment_shader: &'a ShaderModule,
+    pub fragment_entry_point: &'static str,
+    pub texture_formats: &'a [TextureFormat],
+}
+
+pub fn create_render_pipeline(device: &Device, spec: &RenderPipelineSpec) -> RenderPipeline {
     let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
-        label: Some(layout_label),
-        bind_group_layouts: &[bind_group_layout],
-        push_constant_ranges: &[],
+        label: Some(spec.layout_label),
+        bind_group_layouts: spec.bind_group_layouts,
+        push_constant_ranges: spec.push_constant_ranges,
     });
     device.create_render_pipeline(&RenderPipelineDescriptor {
-        label: Some(label),
+        label: Some(spec.label),
         layout: Some(&layout),
         vertex: VertexState {
-            module: vertex_shader,
+            module: spec.vertex_shader,
             entry_point: Some("vertex_main"),
             buffers: &[],
             compilation_options: PipelineCompilationOptions::default(),
         },
         fragment: Some(FragmentState {
-            module: fragment_shader,
-            entry_point: Some("fragment_main"),
+            module: spec.fragment_shader,
+            entry_point: Some(spec.fragment_entry_point),
             compilation_options: PipelineCompilationOptions::default(),
-            targets: &[Some(texture_format.into())],
+            targets: &spec
+                .texture_formats
+                .iter()
+                .map(|&format| Some(format.into()))
+                .collect::<Vec<_>>(),
         }),
         primitive: PrimitiveState {
             topology: PrimitiveTopology::TriangleStrip,
@@ -47,6 +59,29 @@ pub fn create_render_pipeline(
     })
 }
 
+#[cfg_attr(not(test), allow(dead_code))] // there are no compute passes yet
+pub fn create_compute_pipeline(
+    device: &Device,
+    layout_label: &'static str,
+    bind_group_layouts: &[&BindGroupLayout],
+    label: &'static str,
+    shader: &ShaderModule,
+) -> ComputePipeline {
+    let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+        label: Some(layout_label),
+        bind_group_layouts,
+        push_constant_ranges: &[],
+    });
+    device.create_compute_pipeline(&ComputePipelineDescriptor {
+        label: Some(label),
+        layout: Some(&layout),
+        module: shader,
+        entry_point: Some("compute_main"),
+        compilation_options: PipelineCompilationOptions::default(),
+        cache: None,
+    })
+}
+
 pub fn handle_device_errors<F, R>(device: &Device, filter: ErrorFilter, f: F) -> Result<R, Error>
 where
     F: FnOnce() -> R,
@@ -59,11 +94,369 @@ where
     }
 }
 
-pub fn limited_quadratric_delta(current: f32, delta: f32) -> f32 {
+pub async fn handle_device_errors_async<F, R>(
+    device: &Device,
+    filter: ErrorFilter,
+    f: F,
+) -> Result<R, Error>
+where
+    F: FnOnce() -> R,
+{
+    device.push_error_scope(filter);
+    let result = f();
+    match device.pop_error_scope().await {
+        Some(error) => Err(error),
+        None => Ok(result),
+    }
+}
+
+// the `key = value` lines of the small TOML-like config files with their line numbers,
+// comments after `#` and empty lines are skipped
+pub fn parse_key_value_lines(source: &str) -> Result<Vec<(usize, &str, &str)>> {
+    let mut entries = Vec::new();
+    for (index, line) in source.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            bail!("line {line_number}: expected `<key> = <value>`");
+        };
+        entries.push((line_number, key.trim(), value.trim()));
+    }
+    Ok(entries)
+}
+
+// expects the values to be sorted in ascending order
+pub fn percentile(sorted: &[f32], fraction: f32) -> f32 {
+    let index = ((sorted.len() - 1) as f32 * fraction).round() as usize;
+    sorted[index]
+}
+
+// incompatible surfaces report no formats at all
+pub fn preferred_surface_format(capabilities: &SurfaceCapabilities) -> Result<TextureFormat> {
+    const PREFERRED_FORMATS: [TextureFormat; 5] = [
+        TextureFormat::Rgba16Float,
+        TextureFormat::Bgra8UnormSrgb,
+        TextureFormat::Rgba8UnormSrgb,
+        TextureFormat::Bgra8Unorm,
+        TextureFormat::Rgba8Unorm,
+    ];
+    let preferred = PREFERRED_FORMATS
+        .into_iter()
+        .find(|format| capabilities.formats.contains(format));
+    match preferred {
+        Some(format) => Ok(format),
+        None => capabilities
+            .formats
+            .first()
+            .copied()
+            .context("the surface supports no formats, it is incompatible with the adapter"),
+    }
+}
+
+// steps proportionally to the magnitude of the current value, so small values change finely and
+// large ones quickly (the value grows quadratically with repeated steps), tuned by:
+// - LOWER_BOUND: the smallest magnitude used for scaling, so tiny values keep moving
+// - UPPER_BOUND: the largest magnitude used for scaling, so large values do not jump
+// - QUADRATIC_FACTOR: stands in for the magnitude at exactly zero, which would never move away
+// - LINEAR_FACTOR: scales the step relative to the (bounded) magnitude
+pub(crate) fn limited_quadratic_delta(current: f32, delta: f32) -> f32 {
+    const LOWER_BOUND: f32 = 0.0001;
+    const UPPER_BOUND: f32 = 0.1;
+    const QUADRATIC_FACTOR: f32 = 0.025;
+    const LINEAR_FACTOR: f32 = 0.2;
     let factor = if current == 0.0 {
-        0.025
+        QUADRATIC_FACTOR
     } else {
-        current.abs().clamp(0.0001, 0.1)
+        current.abs().clamp(LOWER_BOUND, UPPER_BOUND)
     };
-    0.2 * delta * factor
+    LINEAR_FACTOR * delta * factor
+}
+
+pub fn halton(mut index: u32, base: u32) -> f32 {
+    let mut fraction = 1.0;
+    let mut result = 0.0;
+    while index > 0 {
+        fraction /= base as f32;
+        result += fraction * (index % base) as f32;
+        index /= base;
+    }
+    result
+}
+
+pub fn read_texture(device: &Device, queue: &Queue, texture: &Texture) -> Result<Vec<u8>> {
+    const BYTES_PER_PIXEL: u32 = 4;
+    let width = texture.width();
+    let height = texture.height();
+    let unpadded_bytes_per_row = width * BYTES_PER_PIXEL;
+    let padded_bytes_per_row =
+        unpadded_bytes_per_row.next_multiple_of(COPY_BYTES_PER_ROW_ALIGNMENT);
+    let buffer = device.create_buffer(&BufferDescriptor {
+        label: Some("read_texture_buffer"),
+        size: u64::from(padded_bytes_per_row * height),
+        usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor::default());
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        TexelCopyBufferInfo {
+            buffer: &buffer,
+            layout: TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_bytes_per_row),
+                rows_per_image: Some(height),
+            },
+        },
+        texture.size(),
+    );
+    queue.submit(Some(encoder.finish()));
+    let slice = buffer.slice(..);
+    slice.map_async(MapMode::Read, |result| {
+        result.expect("failed to map read_texture_buffer");
+    });
+    device
+        .poll(PollType::wait_indefinitely())
+        .context("failed to wait for texture read back")?;
+    let data = slice
+        .get_mapped_range()
+        .chunks(padded_bytes_per_row as usize)
+        .flat_map(|row| &row[..unpadded_bytes_per_row as usize])
+        .copied()
+        .collect();
+    buffer.unmap();
+    Ok(data)
+}
+
+pub fn save_png(path: &Path, width: u32, height: u32, rgba: &[u8]) -> Result<()> {
+    let file =
+        File::create(path).with_context(|| format!("failed to create {}", path.display()))?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_source_srgb(png::SrgbRenderingIntent::Perceptual);
+    let mut writer = encoder
+        .write_header()
+        .context("failed to write png header")?;
+    writer
+        .write_image_data(rgba)
+        .context("failed to write png data")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-7,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    fn capabilities(formats: &[TextureFormat]) -> SurfaceCapabilities {
+        SurfaceCapabilities {
+            formats: formats.to_vec(),
+            ..Default::default()
+        }
+    }
+
+    // tests that need a GPU pass trivially on machines without an adapter
+    fn test_device() -> Option<Device> {
+        let instance = wgpu::Instance::default();
+        let adapter =
+            block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default())).ok()?;
+        let (device, _queue) =
+            block_on(adapter.request_device(&wgpu::DeviceDescriptor::default())).ok()?;
+        Some(device)
+    }
+
+    fn shader(device: &Device, source: &'static str) -> ShaderModule {
+        device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        })
+    }
+
+    #[test]
+    fn create_render_pipeline_uses_the_spec() {
+        let Some(device) = test_device() else {
+            return;
+        };
+        let vertex_shader = shader(&device, include_str!("./vertex.wgsl"));
+        let fragment_shader = shader(&device, include_str!("./error.wgsl"));
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[],
+        });
+        let pipeline = handle_device_errors(&device, ErrorFilter::Validation, || {
+            create_render_pipeline(
+                &device,
+                &RenderPipelineSpec {
+                    layout_label: "test_render_pipeline_layout",
+                    bind_group_layouts: &[&bind_group_layout, &bind_group_layout],
+                    push_constant_ranges: &[],
+                    label: "test_render_pipeline",
+                    vertex_shader: &vertex_shader,
+                    fragment_shader: &fragment_shader,
+                    fragment_entry_point: "fragment_main",
+                    texture_formats: &[TextureFormat::Rgba8Unorm],
+                },
+            )
+        })
+        .unwrap();
+        // the layout only has the two bind groups from the spec
+        handle_device_errors(&device, ErrorFilter::Validation, || {
+            pipeline.get_bind_group_layout(1)
+        })
+        .unwrap();
+        handle_device_errors(&device, ErrorFilter::Validation, || {
+            pipeline.get_bind_group_layout(2)
+        })
+        .unwrap_err();
+    }
+
+    #[test]
+    fn create_render_pipeline_rejects_a_missing_entry_point() {
+        let Some(device) = test_device() else {
+            return;
+        };
+        let vertex_shader = shader(&device, include_str!("./vertex.wgsl"));
+        let fragment_shader = shader(&device, include_str!("./error.wgsl"));
+        handle_device_errors(&device, ErrorFilter::Validation, || {
+            create_render_pipeline(
+                &device,
+                &RenderPipelineSpec {
+                    layout_label: "test_render_pipeline_layout",
+                    bind_group_layouts: &[],
+                    push_constant_ranges: &[],
+                    label: "test_render_pipeline",
+                    vertex_shader: &vertex_shader,
+                    fragment_shader: &fragment_shader,
+                    fragment_entry_point: "missing_main",
+                    texture_formats: &[TextureFormat::Rgba8Unorm],
+                },
+            )
+        })
+        .unwrap_err();
+    }
+
+    #[test]
+    fn create_compute_pipeline_uses_every_bind_group_layout() {
+        let Some(device) = test_device() else {
+            return;
+        };
+        let shader = shader(&device, "@compute @workgroup_size(1) fn compute_main() {}");
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[],
+        });
+        let pipeline = handle_device_errors(&device, ErrorFilter::Validation, || {
+            create_compute_pipeline(
+                &device,
+                "test_compute_pipeline_layout",
+                &[&bind_group_layout, &bind_group_layout],
+                "test_compute_pipeline",
+                &shader,
+            )
+        })
+        .unwrap();
+        handle_device_errors(&device, ErrorFilter::Validation, || {
+            pipeline.get_bind_group_layout(1)
+        })
+        .unwrap();
+        handle_device_errors(&device, ErrorFilter::Validation, || {
+            pipeline.get_bind_group_layout(2)
+        })
+        .unwrap_err();
+    }
+
+    #[test]
+    fn key_value_lines_skip_comments_and_empty_lines() {
+        let source = "# header\n\na = 1 # trailing\n  b=\"two\"  \n";
+        let entries = parse_key_value_lines(source).unwrap();
+        assert_eq!(entries, [(3, "a", "1"), (4, "b", "\"two\"")]);
+    }
+
+    #[test]
+    fn key_value_lines_report_the_line_without_equals_sign() {
+        let error = parse_key_value_lines("a = 1\nb 2\n").unwrap_err();
+        assert_eq!(error.to_string(), "line 2: expected `<key> = <value>`");
+    }
+
+    #[test]
+    fn preferred_surface_format_follows_the_priority() {
+        let capabilities =
+            capabilities(&[TextureFormat::Rgba8Unorm, TextureFormat::Bgra8UnormSrgb]);
+        let format = preferred_surface_format(&capabilities).unwrap();
+        assert_eq!(format, TextureFormat::Bgra8UnormSrgb);
+    }
+
+    #[test]
+    fn preferred_surface_format_falls_back_to_the_first_format() {
+        let capabilities = capabilities(&[TextureFormat::Rgb10a2Unorm, TextureFormat::R8Unorm]);
+        let format = preferred_surface_format(&capabilities).unwrap();
+        assert_eq!(format, TextureFormat::Rgb10a2Unorm);
+    }
+
+    #[test]
+    fn preferred_surface_format_fails_without_formats() {
+        assert!(preferred_surface_format(&capabilities(&[])).is_err());
+    }
+
+    #[test]
+    fn zero_delta_does_not_step() {
+        assert_eq!(limited_quadratic_delta(0.05, 0.0), 0.0);
+        assert_eq!(limited_quadratic_delta(0.0, 0.0), 0.0);
+    }
+
+    #[test]
+    fn zero_value_steps_by_quadratic_factor() {
+        assert_close(limited_quadratic_delta(0.0, 1.0), 0.2 * 0.025);
+        assert_close(limited_quadratic_delta(0.0, -1.0), -0.2 * 0.025);
+    }
+
+    #[test]
+    fn quadratic_regime_scales_with_magnitude() {
+        assert_close(limited_quadratic_delta(0.05, 1.0), 0.2 * 0.05);
+        assert_close(limited_quadratic_delta(0.01, 1.0), 0.2 * 0.01);
+    }
+
+    #[test]
+    fn negative_values_scale_with_magnitude() {
+        assert_close(
+            limited_quadratic_delta(-0.05, 1.0),
+            limited_quadratic_delta(0.05, 1.0),
+        );
+    }
+
+    #[test]
+    fn value_near_minimum_is_bounded_below() {
+        assert_close(limited_quadratic_delta(1e-6, 1.0), 0.2 * 0.0001);
+        assert_close(limited_quadratic_delta(0.0001, 1.0), 0.2 * 0.0001);
+    }
+
+    #[test]
+    fn value_near_maximum_is_bounded_above() {
+        assert_close(limited_quadratic_delta(0.1, 1.0), 0.2 * 0.1);
+        assert_close(limited_quadratic_delta(0.099, 1.0), 0.2 * 0.099);
+    }
+
+    #[test]
+    fn large_values_step_like_the_maximum() {
+        assert_close(limited_quadratic_delta(100.0, 1.0), 0.2 * 0.1);
+    }
+
+    #[test]
+    fn large_positive_delta_is_linear() {
+        assert_close(limited_quadratic_delta(0.05, 100.0), 100.0 * 0.2 * 0.05);
+    }
+
+    #[test]
+    fn large_negative_delta_is_linear() {
+        assert_close(limited_quadratic_delta(0.05, -100.0), -100.0 * 0.2 * 0.05);
+    }
 }
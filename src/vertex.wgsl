@@ -1,12 +1,19 @@
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) position: vec2<f32>,
+    @location(1) @interpolate(flat) viewport_index: u32,
 }
 
 @vertex
-fn vertex_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+fn vertex_main(
+    @builtin(vertex_index) vertex_index: u32,
+    @builtin(instance_index) instance_index: u32,
+) -> VertexOutput {
     var out: VertexOutput;
 
+    // each instance covers one viewport
+    out.viewport_index = instance_index;
+
     // turn 4 vertices into a quad that covers the screen
     let x = f32(vertex_index >> 1);
     let y = f32(vertex_index & 1);